@@ -1,9 +1,41 @@
-use crate::config::Config;
-use crate::feed::{FeedItem, FeedSource};
+use crate::config::{Config, ImageProtocol, Retention};
+use crate::feed::{DiscoveredFeed, FeedItem, FeedSource};
+use crate::health::FeedHealth;
 use crate::likes::LikesStatus;
+use crate::query::{ItemContext, Query};
 use crate::reader::Article;
 use crate::seen::SeenStatus;
-use std::collections::HashSet;
+use crate::smart_feed::SmartFeed;
+use image::RgbImage;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum EditField {
+    Name,
+    Url,
+}
+
+/// A row of the Feeds panel: a smart feed or a configured source, by index.
+#[derive(Clone, Copy, PartialEq)]
+pub enum SidebarEntry {
+    Smart(usize),
+    Source(usize),
+}
+
+/// Unread items per feed name, tag and smart feed, shown in the sidebar.
+#[derive(Default)]
+pub struct UnreadCounts {
+    pub feeds: HashMap<String, usize>,
+    pub tags: HashMap<String, usize>,
+    pub smart: Vec<usize>,
+}
+
+/// A page the reader left by following a link, to go back to.
+pub struct ReaderPage {
+    identifier: Option<String>,
+    article: Article,
+    scroll: u16,
+}
 
 #[derive(Clone, Copy, PartialEq)]
 pub enum Focus {
@@ -24,10 +56,8 @@ pub struct App {
     pub input: String,
     pub input_mode: bool,
     pub status: String,
-    pub show_all: bool,
-    pub show_liked: bool,
-    pub show_articles: bool,
-    pub show_videos: bool,
+    pub smart_feeds: Vec<SmartFeed>,
+    pub smart_feed: Option<usize>,
     pub loading: bool,
     pub spinner_frame: usize,
     pub feed_list_state: ListState,
@@ -38,8 +68,28 @@ pub struct App {
     pub background_loading: bool,
     pub current_article: Option<Article>,
     pub article_scroll: u16,
+    pub article_hscroll: u16,
     pub article_loading: bool,
+    pub reader_item: Option<String>,
+    pub reader_history: Vec<ReaderPage>,
+    pub link_hint_mode: bool,
+    pub link_hint_input: String,
+    pub reader_search_mode: bool,
+    pub reader_search_input: String,
+    pub reader_search: Option<String>,
+    /// The lines of the reader's search matches, as of the last frame.
+    pub reader_matches: Vec<usize>,
+    pub reader_match: Option<usize>,
+    /// Images of the pages in the reader, by source. `None` while loading or
+    /// when the image couldn't be loaded.
+    pub article_images: HashMap<String, Option<RgbImage>>,
+    pub image_protocol: ImageProtocol,
+    /// What `image_protocol` comes to on this terminal.
+    pub image_output: ImageProtocol,
     pub filter_mode: bool,
+    pub search_mode: bool,
+    pub search_input: String,
+    pub search_query: Option<String>,
     pub filter: String,
     pub show_tooltips: bool,
     pub tag_editor_mode: bool,
@@ -51,9 +101,28 @@ pub struct App {
     pub paywall_remover: bool,
     pub browser_command: Option<String>,
     pub hide_shorts: bool,
+    pub hide_read: bool,
+    hidden_read_items: HashSet<String>,
+    pub refresh_concurrency: usize,
+    pub retention: Retention,
     pub current_feed: Option<String>,
     pub seen_status: SeenStatus,
     pub likes_status: LikesStatus,
+    pub feed_health: HashMap<String, FeedHealth>,
+    pub show_health: bool,
+    pub offline_downloading: bool,
+    pub feed_picker_mode: bool,
+    pub discovered_feeds: Vec<DiscoveredFeed>,
+    pub discovered_feed_index: usize,
+    pub edit_feed_mode: bool,
+    pub edit_feed_index: usize,
+    pub edit_name: String,
+    pub edit_url: String,
+    pub edit_field: EditField,
+    pub mark_older_mode: bool,
+    pub mark_older_input: String,
+    pub unread_counts: UnreadCounts,
+    cached_items: Vec<FeedItem>,
 }
 
 impl App {
@@ -74,10 +143,8 @@ impl App {
             input: String::new(),
             input_mode: false,
             status: String::new(),
-            show_all: true,
-            show_liked: false,
-            show_articles: false,
-            show_videos: false,
+            smart_feeds: crate::smart_feed::smart_feeds(&Default::default()),
+            smart_feed: None,
             loading: false,
             spinner_frame: 0,
             feed_list_state,
@@ -88,8 +155,24 @@ impl App {
             background_loading: false,
             current_article: None,
             article_scroll: 0,
+            article_hscroll: 0,
             article_loading: false,
+            reader_item: None,
+            reader_history: Vec::new(),
+            link_hint_mode: false,
+            link_hint_input: String::new(),
+            reader_search_mode: false,
+            reader_search_input: String::new(),
+            reader_search: None,
+            reader_matches: Vec::new(),
+            reader_match: None,
+            article_images: HashMap::new(),
+            image_protocol: ImageProtocol::Auto,
+            image_output: ImageProtocol::None,
             filter_mode: false,
+            search_mode: false,
+            search_input: String::new(),
+            search_query: None,
             filter: String::new(),
             show_tooltips: true,
             tag_editor_mode: false,
@@ -101,9 +184,28 @@ impl App {
             paywall_remover: false,
             browser_command: None,
             hide_shorts: false,
+            hide_read: false,
+            hidden_read_items: HashSet::new(),
+            refresh_concurrency: 8,
+            retention: Retention::default(),
             current_feed: None,
             seen_status: SeenStatus::load().unwrap_or_default(),
             likes_status: LikesStatus::load().unwrap_or_default(),
+            feed_health: HashMap::new(),
+            show_health: false,
+            offline_downloading: false,
+            feed_picker_mode: false,
+            discovered_feeds: Vec::new(),
+            discovered_feed_index: 0,
+            edit_feed_mode: false,
+            edit_feed_index: 0,
+            edit_name: String::new(),
+            edit_url: String::new(),
+            edit_field: EditField::Name,
+            mark_older_mode: false,
+            mark_older_input: String::new(),
+            unread_counts: UnreadCounts::default(),
+            cached_items: Vec::new(),
         }
     }
 
@@ -169,10 +271,22 @@ impl App {
             self.invidious_instance = config.get_invidious_instance().to_string();
             self.show_tooltips = config.settings.show_tooltips;
             self.paywall_remover = config.settings.paywall_remover;
+            self.retention = config.settings.retention();
             self.browser_command = config.settings.browser_command;
             self.hide_shorts = config.settings.hide_shorts;
+            self.refresh_concurrency = config.settings.refresh_concurrency;
+            self.image_protocol = config.settings.image_protocol;
+            self.smart_feeds = crate::smart_feed::smart_feeds(&config.saved_searches);
             self.sources = config.sources;
         }
+        self.image_output = crate::graphics::resolve(self.image_protocol);
+        crate::cache::migrate_legacy_caches(&self.sources);
+        self.feed_health = self
+            .sources
+            .iter()
+            .map(|s| (s.name.clone(), FeedHealth::load(s)))
+            .collect();
+        self.reload_unread_counts();
     }
 
     pub fn save_config(&self) {
@@ -185,37 +299,43 @@ impl App {
                 paywall_remover: self.paywall_remover,
                 browser_command: self.browser_command.clone(),
                 hide_shorts: self.hide_shorts,
+                refresh_concurrency: self.refresh_concurrency,
+                max_items_per_feed: self.retention.max_items,
+                max_age_days: self.retention.max_age_days,
+                image_protocol: self.image_protocol,
             },
+            saved_searches: crate::smart_feed::saved_searches(&self.smart_feeds),
         };
         config.save();
     }
 
     fn feed_list_len(&self) -> usize {
-        let filtered_count = if self.filter.is_empty() {
-            self.sources.len()
-        } else {
-            self.get_filtered_sources().len()
-        };
+        self.sidebar_entries().len()
+    }
 
-        let mut offset = 0;
-        if self.show_all && self.filter.is_empty() {
-            offset += 1;
-        }
-        if self.filter.is_empty() {
-            offset += 1;
-            offset += 1;
-            offset += 1;
-        }
+    /// The rows of the Feeds panel in display order: smart feeds first, then
+    /// sources, both narrowed by the filter.
+    pub fn sidebar_entries(&self) -> Vec<SidebarEntry> {
+        let filter_lower = self.filter.to_lowercase();
+        let smart = self
+            .smart_feeds
+            .iter()
+            .enumerate()
+            .filter(|(_, feed)| feed.name.to_lowercase().contains(&filter_lower))
+            .map(|(idx, _)| SidebarEntry::Smart(idx));
+        let sources = self
+            .get_filtered_sources()
+            .into_iter()
+            .map(|(idx, _)| SidebarEntry::Source(idx));
+        smart.chain(sources).collect()
+    }
 
-        filtered_count + offset
+    pub fn selected_entry(&self) -> Option<SidebarEntry> {
+        self.sidebar_entries().get(self.feed_index).copied()
     }
 
     fn item_list_len(&self) -> usize {
-        if self.filter.is_empty() {
-            self.items.len()
-        } else {
-            self.get_filtered_items().len()
-        }
+        self.get_filtered_items().len()
     }
 
     pub fn next(&mut self) {
@@ -311,11 +431,52 @@ impl App {
             },
             url,
             tags: Vec::new(),
+            id: None,
+            max_items: None,
+            max_age_days: None,
         };
         self.sources.push(source);
         self.save_config();
     }
 
+    pub fn start_feed_picker(&mut self, feeds: Vec<DiscoveredFeed>) {
+        self.feed_picker_mode = true;
+        self.discovered_feeds = feeds;
+        self.discovered_feed_index = 0;
+        self.status = format!("Found {} feeds, pick one to add", self.discovered_feeds.len());
+    }
+
+    pub fn next_discovered_feed(&mut self) {
+        if !self.discovered_feeds.is_empty() {
+            self.discovered_feed_index = (self.discovered_feed_index + 1) % self.discovered_feeds.len();
+        }
+    }
+
+    pub fn previous_discovered_feed(&mut self) {
+        if !self.discovered_feeds.is_empty() {
+            self.discovered_feed_index = self
+                .discovered_feed_index
+                .checked_sub(1)
+                .unwrap_or(self.discovered_feeds.len() - 1);
+        }
+    }
+
+    pub fn submit_feed_picker(&mut self) -> Option<String> {
+        let url = self
+            .discovered_feeds
+            .get(self.discovered_feed_index)
+            .map(|f| f.url.clone());
+        self.cancel_feed_picker();
+        url
+    }
+
+    pub fn cancel_feed_picker(&mut self) {
+        self.feed_picker_mode = false;
+        self.discovered_feeds.clear();
+        self.discovered_feed_index = 0;
+        self.status.clear();
+    }
+
     pub fn cancel_input(&mut self) {
         self.input_mode = false;
         self.input.clear();
@@ -373,63 +534,94 @@ impl App {
     }
 
     pub fn get_filtered_items(&self) -> Vec<(usize, &FeedItem)> {
-        let mut items: Vec<(usize, &FeedItem)> = if self.filter.is_empty() {
-            self.items.iter().enumerate().collect()
-        } else {
-            let filter_lower = self.filter.to_lowercase();
-            self.items
-                .iter()
-                .enumerate()
-                .filter(|(_, i)| i.title.to_lowercase().contains(&filter_lower))
-                .collect()
-        };
-
-        if self.hide_shorts {
-            items.retain(|(_, item)| !item.is_youtube_short);
+        let query = Query::parse(&format!("{} {}", self.view_query(), self.filter));
+        let mut items = self.filter_items(&self.items, &query);
+        if self.hide_read {
+            items.retain(|(_, item)| {
+                let identifier = crate::seen::get_item_identifier(&item.link, &item.title);
+                !self.hidden_read_items.contains(&identifier)
+            });
         }
+        items
+    }
 
-        if self.show_liked {
-            items.retain(|(_, item)| item.liked);
+    fn filter_items<'a>(&self, items: &'a [FeedItem], query: &Query) -> Vec<(usize, &'a FeedItem)> {
+        if query.is_empty() {
+            return items.iter().enumerate().collect();
         }
 
-        if self.show_articles {
-            items.retain(|(_, item)| self.is_article_item(item));
+        let tags: HashMap<&str, &[String]> = self
+            .sources
+            .iter()
+            .map(|s| (s.name.as_str(), s.tags.as_slice()))
+            .collect();
+        items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| {
+                let context = ItemContext {
+                    tags: tags.get(item.source_name.as_str()).copied().unwrap_or_default(),
+                    is_video: self.is_video_item(item),
+                };
+                query.matches(item, &context)
+            })
+            .collect()
+    }
+
+    /// The list toggles and active smart feed expressed as a query, applied under the user's filter.
+    fn view_query(&self) -> String {
+        let mut terms = Vec::new();
+        if self.hide_shorts {
+            terms.push("-is:short");
+        }
+        if let Some(feed) = self.smart_feed.and_then(|idx| self.smart_feeds.get(idx)) {
+            terms.push(feed.query.as_str());
         }
+        terms.join(" ")
+    }
+
+    pub fn start_search(&mut self) {
+        self.search_mode = true;
+        self.search_input = self.search_query.clone().unwrap_or_default();
+        self.status = "Search all feeds:".to_string();
+    }
 
-        if self.show_videos {
-            items.retain(|(_, item)| self.is_video_item(item));
+    pub fn submit_search(&mut self) {
+        self.search_mode = false;
+        let query = self.search_input.trim().to_string();
+        if query.is_empty() {
+            self.status.clear();
+            return;
         }
 
-        items
+        self.items = crate::cache::search(&self.sources, &query);
+        self.update_items_seen_status();
+        self.update_items_like_status();
+        self.filter.clear();
+        self.smart_feed = None;
+        self.item_index = 0;
+        self.item_list_state.select(Some(0));
+        self.focus = Focus::Items;
+        self.status = format!("{} results for \"{}\"", self.items.len(), query);
+        self.search_query = Some(query);
+    }
+
+    pub fn cancel_search(&mut self) {
+        self.search_mode = false;
+        self.search_input.clear();
+        self.status.clear();
     }
 
     pub fn delete_selected(&mut self) {
         if self.focus == Focus::Feeds {
-            let mut offset = 0;
-            if self.show_all && self.filter.is_empty() {
-                if self.feed_index == 0 {
-                    return;
-                }
-                offset += 1;
-            }
-            if self.filter.is_empty() {
-                if self.feed_index == offset || self.feed_index == offset + 1 || self.feed_index == offset + 2 {
-                    return;
-                }
-                offset += 1;
-                offset += 1;
-                offset += 1;
-            }
-            let filtered_sources = self.get_filtered_sources();
-            let display_idx = self.feed_index - offset;
-            if display_idx < filtered_sources.len() {
-                let (original_idx, _) = filtered_sources[display_idx];
+            if let Some(original_idx) = self.selected_source_index() {
                 self.sources.remove(original_idx);
                 let len = self.feed_list_len();
                 if self.feed_index >= len && len > 0 {
                     self.feed_index = len - 1;
                 }
                 self.save_config();
+                self.reload_unread_counts();
                 self.status = "Feed removed".to_string();
             }
         }
@@ -482,33 +674,255 @@ impl App {
     }
 
     pub fn can_open_in_reader(&self) -> bool {
-        if self.focus == Focus::Items {
-            if let Some(item) = self.get_selected_item() {
-                if let Some(link) = &item.link {
-                    return !self.is_youtube_link(link);
-                }
+        self.get_selected_item()
+            .is_some_and(|item| self.is_readable(item))
+    }
+
+    fn is_readable(&self, item: &FeedItem) -> bool {
+        item.link.as_ref().is_some_and(|link| !self.is_youtube_link(link))
+    }
+
+    /// Selects the closest item after (or before) the selected one that the
+    /// reader can show, skipping videos.
+    pub fn select_adjacent_readable(&mut self, forward: bool) -> bool {
+        let items = self.get_filtered_items();
+        let found = if forward {
+            items
+                .iter()
+                .enumerate()
+                .skip(self.item_index + 1)
+                .find(|(_, (_, item))| self.is_readable(item))
+                .map(|(idx, _)| idx)
+        } else {
+            items
+                .iter()
+                .take(self.item_index)
+                .rposition(|(_, item)| self.is_readable(item))
+        };
+        match found {
+            Some(idx) => {
+                self.item_index = idx;
+                self.item_list_state.select(Some(idx));
+                true
+            }
+            None => {
+                self.status = if forward {
+                    "No next article".to_string()
+                } else {
+                    "No previous article".to_string()
+                };
+                false
             }
         }
-        false
+    }
+
+    /// The identifier and link of the next item the reader can show, for
+    /// prefetching.
+    pub fn next_readable_item(&self) -> Option<(String, String)> {
+        self.get_filtered_items()
+            .into_iter()
+            .skip(self.item_index + 1)
+            .find(|(_, item)| self.is_readable(item))
+            .map(|(_, item)| {
+                (
+                    crate::seen::get_item_identifier(&item.link, &item.title),
+                    item.link.clone().unwrap_or_default(),
+                )
+            })
     }
 
     pub fn get_selected_url(&self) -> Option<String> {
         self.get_selected_item().and_then(|item| item.link.clone())
     }
 
+    pub fn get_articles_to_download(&self) -> Vec<(String, String)> {
+        self.get_filtered_items()
+            .into_iter()
+            .filter(|(_, item)| !item.seen)
+            .filter_map(|(_, item)| {
+                let link = item.link.as_ref()?;
+                if self.is_youtube_link(link) {
+                    return None;
+                }
+                let identifier = crate::seen::get_item_identifier(&item.link, &item.title);
+                if crate::cache::has_article(&identifier) {
+                    return None;
+                }
+                Some((identifier, link.clone()))
+            })
+            .collect()
+    }
+
     pub fn show_article(&mut self, article: crate::reader::Article) {
         self.current_article = Some(article);
         self.article_scroll = 0;
+        self.article_hscroll = 0;
+        self.clear_reader_search();
         self.focus = Focus::Reader;
         self.article_loading = false;
     }
 
     pub fn close_reader(&mut self) {
         self.current_article = None;
+        self.reader_item = None;
+        self.reader_history.clear();
+        self.article_images.clear();
+        self.cancel_link_hint();
+        self.clear_reader_search();
         self.article_scroll = 0;
+        self.article_hscroll = 0;
         self.focus = Focus::Items;
     }
 
+    /// The address of the page in the reader: the selected item's, or the
+    /// followed link's.
+    pub fn reader_url(&self) -> Option<String> {
+        if self.reader_history.is_empty() {
+            self.get_selected_url()
+        } else {
+            self.reader_item.clone()
+        }
+    }
+
+    pub fn start_link_hint(&mut self) {
+        let count = self.current_article.as_ref().map_or(0, |a| a.links().len());
+        if count == 0 {
+            return;
+        }
+        self.link_hint_mode = true;
+        self.link_hint_input.clear();
+    }
+
+    // Digits that would point past the last link are ignored.
+    pub fn push_link_hint_digit(&mut self, digit: char) {
+        let count = self.current_article.as_ref().map_or(0, |a| a.links().len());
+        let input = format!("{}{}", self.link_hint_input, digit);
+        if input.parse::<usize>().is_ok_and(|n| n <= count) {
+            self.link_hint_input = input;
+        }
+    }
+
+    /// Leaves link hint mode, returning the link whose number was typed.
+    pub fn take_link_hint(&mut self) -> Option<String> {
+        let input = std::mem::take(&mut self.link_hint_input);
+        self.link_hint_mode = false;
+        let number: usize = input.parse().ok()?;
+        let links = self.current_article.as_ref()?.links();
+        links.get(number.checked_sub(1)?).cloned()
+    }
+
+    pub fn cancel_link_hint(&mut self) {
+        self.link_hint_mode = false;
+        self.link_hint_input.clear();
+    }
+
+    /// Keeps the page in the reader to come back to, and makes `identifier`
+    /// the one being loaded.
+    pub fn push_reader_page(&mut self, identifier: String) {
+        if let Some(article) = self.current_article.take() {
+            self.reader_history.push(ReaderPage {
+                identifier: self.reader_item.take(),
+                article,
+                scroll: self.article_scroll,
+            });
+        }
+        self.reader_item = Some(identifier);
+        self.article_scroll = 0;
+        self.article_hscroll = 0;
+        self.clear_reader_search();
+    }
+
+    /// Goes back to the page a link was followed from, or leaves the reader.
+    pub fn leave_reader_page(&mut self) {
+        if !self.reader_back() {
+            self.close_reader();
+        }
+    }
+
+    /// Returns to the page a link was followed from, if any.
+    pub fn reader_back(&mut self) -> bool {
+        let Some(page) = self.reader_history.pop() else {
+            return false;
+        };
+        self.reader_item = page.identifier;
+        self.current_article = Some(page.article);
+        self.article_scroll = page.scroll;
+        self.article_hscroll = 0;
+        self.article_loading = false;
+        self.clear_reader_search();
+        true
+    }
+
+    pub fn start_reader_search(&mut self) {
+        self.reader_search_mode = true;
+        self.reader_search_input.clear();
+    }
+
+    pub fn submit_reader_search(&mut self) {
+        self.reader_search_mode = false;
+        let query = self.reader_search_input.trim().to_string();
+        self.clear_reader_search();
+        if !query.is_empty() {
+            self.reader_search = Some(query);
+        }
+    }
+
+    pub fn cancel_reader_search(&mut self) {
+        self.reader_search_mode = false;
+        self.reader_search_input.clear();
+    }
+
+    pub fn clear_reader_search(&mut self) {
+        self.reader_search = None;
+        self.reader_matches.clear();
+        self.reader_match = None;
+    }
+
+    /// Takes the lines the reader found search matches on. Matches are only
+    /// known once the article is laid out, so a new search jumps to its
+    /// first match below the top of the view here.
+    pub fn update_reader_matches(&mut self, matches: Vec<usize>) {
+        self.reader_matches = matches;
+        match self.reader_match {
+            _ if self.reader_matches.is_empty() => self.reader_match = None,
+            None if self.reader_search.is_some() => {
+                let scroll = usize::from(self.article_scroll);
+                let first = self
+                    .reader_matches
+                    .iter()
+                    .position(|&line| line >= scroll)
+                    .unwrap_or(0);
+                self.select_reader_match(first);
+            }
+            Some(current) if current >= self.reader_matches.len() => {
+                self.reader_match = Some(self.reader_matches.len() - 1);
+            }
+            _ => {}
+        }
+    }
+
+    pub fn next_reader_match(&mut self) {
+        let count = self.reader_matches.len();
+        if count > 0 {
+            self.select_reader_match(self.reader_match.map_or(0, |i| (i + 1) % count));
+        }
+    }
+
+    pub fn previous_reader_match(&mut self) {
+        let count = self.reader_matches.len();
+        if count > 0 {
+            self.select_reader_match(self.reader_match.map_or(count - 1, |i| (i + count - 1) % count));
+        }
+    }
+
+    // Leaves a few lines above the match for context.
+    fn select_reader_match(&mut self, index: usize) {
+        const CONTEXT: usize = 3;
+        self.reader_match = Some(index);
+        let line = self.reader_matches[index].saturating_sub(CONTEXT);
+        self.article_scroll = u16::try_from(line).unwrap_or(u16::MAX);
+    }
+
     pub fn scroll_article_down(&mut self) {
         self.article_scroll = self.article_scroll.saturating_add(1);
     }
@@ -525,6 +939,42 @@ impl App {
         self.article_scroll = self.article_scroll.saturating_sub(page_size);
     }
 
+    /// Images of the article in the reader that haven't been asked for yet.
+    /// They are marked as loading.
+    pub fn request_article_images(&mut self) -> Vec<String> {
+        const MAX_IMAGES: usize = 30;
+        if self.image_output == ImageProtocol::None {
+            return Vec::new();
+        }
+        let Some(article) = &self.current_article else {
+            return Vec::new();
+        };
+        let sources: Vec<String> = crate::document::images(&article.document())
+            .into_iter()
+            .filter(|src| src.starts_with("http"))
+            .take(MAX_IMAGES)
+            .filter(|src| !self.article_images.contains_key(src))
+            .collect();
+        for src in &sources {
+            self.article_images.insert(src.clone(), None);
+        }
+        sources
+    }
+
+    /// Scrolls code blocks sideways, up to the end of the longest line.
+    pub fn scroll_article_right(&mut self, columns: u16) {
+        let widest = self
+            .current_article
+            .as_ref()
+            .map_or(0, |article| crate::document::code_width(&article.document()));
+        let widest = u16::try_from(widest).unwrap_or(u16::MAX);
+        self.article_hscroll = self.article_hscroll.saturating_add(columns).min(widest);
+    }
+
+    pub fn scroll_article_left(&mut self, columns: u16) {
+        self.article_hscroll = self.article_hscroll.saturating_sub(columns);
+    }
+
     fn is_youtube_link(&self, url: &str) -> bool {
         url.contains("youtube.com") || url.contains("youtu.be")
     }
@@ -537,10 +987,6 @@ impl App {
         }
     }
 
-    fn is_article_item(&self, item: &FeedItem) -> bool {
-        !self.is_video_item(item)
-    }
-
     fn convert_to_invidious(&self, url: &str) -> String {
         if url.contains("youtube.com/watch?v=") {
             if let Some(video_id) = url.split("v=").nth(1) {
@@ -579,26 +1025,7 @@ impl App {
 
     pub fn start_tag_editor(&mut self) {
         if self.focus == Focus::Feeds {
-            let mut offset = 0;
-            if self.show_all && self.filter.is_empty() {
-                if self.feed_index == 0 {
-                    return;
-                }
-                offset += 1;
-            }
-            if self.filter.is_empty() {
-                if self.feed_index == offset || self.feed_index == offset + 1 || self.feed_index == offset + 2 {
-                    return;
-                }
-                offset += 1;
-                offset += 1;
-                offset += 1;
-            }
-            let filtered_sources = self.get_filtered_sources();
-            let display_idx = self.feed_index - offset;
-
-            if display_idx < filtered_sources.len() {
-                let (original_idx, _) = filtered_sources[display_idx];
+            if let Some(original_idx) = self.selected_source_index() {
                 self.tag_editor_mode = true;
                 self.tag_input.clear();
                 self.editing_tags = self.sources[original_idx].tags.clone();
@@ -611,6 +1038,97 @@ impl App {
         }
     }
 
+    fn selected_source_index(&self) -> Option<usize> {
+        match self.selected_entry()? {
+            SidebarEntry::Source(idx) => Some(idx),
+            SidebarEntry::Smart(_) => None,
+        }
+    }
+
+    pub fn start_edit_feed(&mut self) {
+        if self.focus != Focus::Feeds {
+            return;
+        }
+        if let Some(idx) = self.selected_source_index() {
+            self.edit_feed_mode = true;
+            self.edit_feed_index = idx;
+            self.edit_name = self.sources[idx].name.clone();
+            self.edit_url = self.sources[idx].url.clone();
+            self.edit_field = EditField::Name;
+            self.status = "Edit feed (Tab to switch field, Enter to save):".to_string();
+        }
+    }
+
+    pub fn edit_input_mut(&mut self) -> &mut String {
+        match self.edit_field {
+            EditField::Name => &mut self.edit_name,
+            EditField::Url => &mut self.edit_url,
+        }
+    }
+
+    pub fn toggle_edit_field(&mut self) {
+        self.edit_field = match self.edit_field {
+            EditField::Name => EditField::Url,
+            EditField::Url => EditField::Name,
+        };
+    }
+
+    pub fn submit_edit_feed(&mut self) {
+        let name = self.edit_name.trim().to_string();
+        let url = self.edit_url.trim().to_string();
+        if name.is_empty() || url.is_empty() {
+            self.status = "Name and URL can't be empty".to_string();
+            return;
+        }
+        let idx = self.edit_feed_index;
+        if self
+            .sources
+            .iter()
+            .enumerate()
+            .any(|(i, s)| i != idx && s.name == name)
+        {
+            self.status = format!("A feed named '{}' already exists", name);
+            return;
+        }
+
+        let old_name = self.sources[idx].name.clone();
+        let url_changed = self.sources[idx].url != url;
+
+        if old_name != name {
+            if let Some(health) = self.feed_health.remove(&old_name) {
+                self.feed_health.insert(name.clone(), health);
+            }
+            for item in self.items.iter_mut().chain(self.cached_items.iter_mut()) {
+                if item.source_name == old_name {
+                    item.source_name = name.clone();
+                }
+            }
+            if self.current_feed.as_deref() == Some(old_name.as_str()) {
+                self.current_feed = Some(name.clone());
+            }
+        }
+        if url_changed {
+            crate::cache::forget_validators(&self.sources[idx]);
+            // Keep the derived id so the cache follows the feed to its new URL
+            let id = self.sources[idx].id();
+            self.sources[idx].id = Some(id);
+        }
+
+        self.sources[idx].name = name;
+        self.sources[idx].url = url;
+        self.save_config();
+        self.recount_unread();
+        self.cancel_edit_feed();
+        self.status = "Feed updated".to_string();
+    }
+
+    pub fn cancel_edit_feed(&mut self) {
+        self.edit_feed_mode = false;
+        self.edit_name.clear();
+        self.edit_url.clear();
+        self.status.clear();
+    }
+
     pub fn add_tag_from_input(&mut self) {
         let tag = self.tag_input.trim().to_string();
         if !tag.is_empty() && !self.editing_tags.contains(&tag) {
@@ -643,26 +1161,10 @@ impl App {
     }
 
     pub fn submit_tags(&mut self) {
-        if self.show_all && self.feed_index == 0 && self.filter.is_empty() {
-            self.cancel_tag_editor();
-            return;
-        }
-        let filtered_sources = self.get_filtered_sources();
-        let mut offset = 0;
-        if self.show_all && self.filter.is_empty() {
-            offset += 1;
-        }
-        if self.filter.is_empty() {
-            offset += 1;
-            offset += 1;
-            offset += 1;
-        }
-        let display_idx = self.feed_index - offset;
-
-        if display_idx < filtered_sources.len() {
-            let (original_idx, _) = filtered_sources[display_idx];
+        if let Some(original_idx) = self.selected_source_index() {
             self.sources[original_idx].tags = self.editing_tags.clone();
             self.save_config();
+            self.recount_unread();
             self.status = "Tags updated".to_string();
         }
 
@@ -682,7 +1184,11 @@ impl App {
             let tags = self.get_all_tags();
             if self.tag_index < tags.len() {
                 let tag = tags[self.tag_index].clone();
+                self.search_query = None;
+                self.smart_feed = None;
                 self.items = self.get_items_by_tag(&tag);
+                self.update_items_seen_status();
+                self.update_items_like_status();
                 self.item_index = 0;
                 self.item_list_state.select(Some(0));
                 self.focus = Focus::Items;
@@ -695,12 +1201,12 @@ impl App {
         let mut tagged_items = Vec::new();
         for source in &self.sources {
             if source.tags.contains(&tag.to_string()) {
-                if let Some(feed_items) = crate::cache::load_cached_items(&source.name) {
+                if let Some(feed_items) = crate::cache::load_cached_items(source) {
                     tagged_items.extend(feed_items);
                 }
             }
         }
-        tagged_items.sort_by(|a, b| b.date.cmp(&a.date));
+        tagged_items.sort_by_key(|item| std::cmp::Reverse(item.date));
         tagged_items
     }
 
@@ -711,11 +1217,189 @@ impl App {
             .collect()
     }
 
+    /// Call after replacing `items`. Items that are read at this point are
+    /// the ones hidden by `hide_read` until the list is loaded again.
     pub fn update_items_seen_status(&mut self) {
+        self.sync_seen_flags();
+        self.hidden_read_items = self
+            .items
+            .iter()
+            .filter(|item| item.seen)
+            .map(|item| crate::seen::get_item_identifier(&item.link, &item.title))
+            .collect();
+    }
+
+    // Items read while the list is shown stay visible, so the selection
+    // doesn't jump when the selected item is opened.
+    fn sync_seen_flags(&mut self) {
         for item in &mut self.items {
             let identifier = crate::seen::get_item_identifier(&item.link, &item.title);
             item.seen = self.seen_status.is_seen(&identifier);
         }
+        self.recount_unread();
+    }
+
+    pub fn toggle_hide_read(&mut self) {
+        self.hide_read = !self.hide_read;
+        self.update_items_seen_status();
+        self.clamp_item_index();
+        self.status = if self.hide_read {
+            "Hiding read items".to_string()
+        } else {
+            "Showing read items".to_string()
+        };
+    }
+
+    fn clamp_item_index(&mut self) {
+        let len = self.item_list_len();
+        if self.item_index >= len {
+            self.item_index = len.saturating_sub(1);
+        }
+        self.item_list_state.select(Some(self.item_index));
+    }
+
+    /// Selects the next unread item in the list, moving on to the next feed
+    /// with unread items once the current one has none left.
+    pub fn next_unread(&mut self) -> bool {
+        let next = self
+            .get_filtered_items()
+            .iter()
+            .enumerate()
+            .skip(self.item_index + 1)
+            .find(|(_, (_, item))| !item.seen)
+            .map(|(idx, _)| idx);
+        match next {
+            Some(idx) => {
+                self.item_index = idx;
+                self.item_list_state.select(Some(idx));
+                true
+            }
+            None if self.current_feed.is_some() => self.next_unread_feed(),
+            None => {
+                self.status = "No more unread items".to_string();
+                false
+            }
+        }
+    }
+
+    pub fn previous_unread(&mut self) -> bool {
+        let previous = self
+            .get_filtered_items()
+            .iter()
+            .take(self.item_index)
+            .rposition(|(_, item)| !item.seen);
+        match previous {
+            Some(idx) => {
+                self.item_index = idx;
+                self.item_list_state.select(Some(idx));
+                true
+            }
+            None => {
+                self.status = "No previous unread items".to_string();
+                false
+            }
+        }
+    }
+
+    /// Opens the next feed after the current one, in sidebar order, that has
+    /// unread items, from the cache, with its first unread item selected.
+    pub fn next_unread_feed(&mut self) -> bool {
+        let current = self
+            .current_feed
+            .as_ref()
+            .and_then(|name| self.sources.iter().position(|s| &s.name == name));
+        let start = current.map(|idx| idx + 1).unwrap_or(0);
+        let len = self.sources.len();
+        let next = (0..len)
+            .map(|offset| (start + offset) % len)
+            .filter(|idx| Some(*idx) != current)
+            .find(|idx| {
+                self.unread_counts
+                    .feeds
+                    .get(&self.sources[*idx].name)
+                    .is_some_and(|count| *count > 0)
+            });
+        let Some(idx) = next else {
+            self.status = "No more unread items".to_string();
+            return false;
+        };
+
+        let source = self.sources[idx].clone();
+        self.items = crate::cache::load_cached_items(&source).unwrap_or_default();
+        self.update_items_seen_status();
+        self.update_items_like_status();
+        self.current_feed = Some(source.name.clone());
+        self.smart_feed = None;
+        self.search_query = None;
+        self.filter.clear();
+        if let Some(pos) = self
+            .sidebar_entries()
+            .iter()
+            .position(|entry| *entry == SidebarEntry::Source(idx))
+        {
+            self.feed_index = pos;
+            self.feed_list_state.select(Some(pos));
+        }
+        if self.focus == Focus::Feeds {
+            self.focus = Focus::Items;
+        }
+
+        self.item_index = self
+            .get_filtered_items()
+            .iter()
+            .position(|(_, item)| !item.seen)
+            .unwrap_or(0);
+        self.item_list_state.select(Some(self.item_index));
+        let unread = self.unread_counts.feeds.get(&source.name).copied().unwrap_or(0);
+        self.status = format!("{}: {} unread", source.name, unread);
+        true
+    }
+
+    /// Reloads every feed's cache for the sidebar counts. Use
+    /// `update_cached_feed` when only one feed changed.
+    pub fn reload_unread_counts(&mut self) {
+        self.cached_items = crate::cache::load_all_cached(&self.sources);
+        self.recount_unread();
+    }
+
+    pub fn update_cached_feed(&mut self, source_name: &str, items: &[FeedItem]) {
+        self.cached_items.retain(|item| item.source_name != source_name);
+        self.cached_items.extend(items.iter().cloned());
+        self.recount_unread();
+    }
+
+    fn recount_unread(&mut self) {
+        for item in &mut self.cached_items {
+            let identifier = crate::seen::get_item_identifier(&item.link, &item.title);
+            item.seen = self.seen_status.is_seen(&identifier);
+            item.liked = self.likes_status.is_liked(&identifier);
+        }
+
+        let base = if self.hide_shorts { "is:unread -is:short" } else { "is:unread" };
+        let unread = self.filter_items(&self.cached_items, &Query::parse(base));
+        let mut feeds: HashMap<String, usize> = HashMap::new();
+        for (_, item) in &unread {
+            *feeds.entry(item.source_name.clone()).or_default() += 1;
+        }
+
+        let mut tags: HashMap<String, usize> = HashMap::new();
+        for source in &self.sources {
+            let count = feeds.get(&source.name).copied().unwrap_or(0);
+            for tag in &source.tags {
+                *tags.entry(tag.clone()).or_default() += count;
+            }
+        }
+
+        let smart = self
+            .smart_feeds
+            .iter()
+            .map(|feed| {
+                let query = Query::parse(&format!("{} {}", base, feed.query));
+                self.filter_items(&self.cached_items, &query).len()
+            })
+            .collect();
+
+        self.unread_counts = UnreadCounts { feeds, tags, smart };
     }
 
     pub fn mark_selected_as_seen(&mut self) {
@@ -728,8 +1412,146 @@ impl App {
             if let Err(e) = self.seen_status.save() {
                 self.status = format!("Failed to save seen status: {}", e);
             }
-            self.update_items_seen_status();
+            self.sync_seen_flags();
+        }
+    }
+
+    pub fn toggle_selected_seen(&mut self) {
+        let identifier = self.get_selected_item().map(|item| {
+            crate::seen::get_item_identifier(&item.link, &item.title)
+        });
+
+        if let Some(id) = identifier {
+            let is_seen = self.seen_status.toggle_seen(&id);
+            if let Err(e) = self.seen_status.save() {
+                self.status = format!("Failed to save seen status: {}", e);
+            } else {
+                self.status = if is_seen {
+                    "Marked as read".to_string()
+                } else {
+                    "Marked as unread".to_string()
+                };
+            }
+            self.sync_seen_flags();
+        }
+    }
+
+    /// Marks every item in the list as it is currently shown, after the
+    /// filter, tag, feed or smart feed, as read.
+    pub fn mark_view_as_seen(&mut self) {
+        let identifiers: Vec<String> = self
+            .get_filtered_items()
+            .iter()
+            .map(|(_, item)| crate::seen::get_item_identifier(&item.link, &item.title))
+            .collect();
+        self.status = match self.mark_identifiers_seen(&identifiers) {
+            Ok(count) => format!("Marked {} items as read", count),
+            Err(e) => format!("Failed to save seen status: {}", e),
+        };
+    }
+
+    /// Marks all cached items of the feed selected in the sidebar as read,
+    /// whether or not it is the one being shown.
+    pub fn mark_selected_feed_as_seen(&mut self) {
+        let (name, identifiers) = match self.selected_entry() {
+            Some(SidebarEntry::Source(idx)) => {
+                let source = &self.sources[idx];
+                let items = crate::cache::load_cached_items(source).unwrap_or_default();
+                let identifiers = items
+                    .iter()
+                    .map(|item| crate::seen::get_item_identifier(&item.link, &item.title))
+                    .collect();
+                (source.name.clone(), identifiers)
+            }
+            Some(SidebarEntry::Smart(idx)) => {
+                let feed = &self.smart_feeds[idx];
+                let query = Query::parse(&feed.query);
+                (feed.name.clone(), self.cached_identifiers_matching(&query))
+            }
+            None => return,
+        };
+        self.status = match self.mark_identifiers_seen(&identifiers) {
+            Ok(count) => format!("Marked {} items in {} as read", count, name),
+            Err(e) => format!("Failed to save seen status: {}", e),
+        };
+    }
+
+    pub fn start_mark_older(&mut self) {
+        self.mark_older_mode = true;
+        self.mark_older_input.clear();
+        self.status = "Mark items older than how many days as read:".to_string();
+    }
+
+    pub fn submit_mark_older(&mut self) {
+        self.mark_older_mode = false;
+        let days: u32 = match self.mark_older_input.trim().parse() {
+            Ok(days) => days,
+            Err(_) => {
+                self.status = format!("Not a number of days: {}", self.mark_older_input.trim());
+                return;
+            }
+        };
+
+        let cutoff = chrono::Duration::try_days(i64::from(days)).and_then(|age| chrono::Utc::now().checked_sub_signed(age));
+        let Some(cutoff) = cutoff else {
+            self.status = format!("Too many days: {}", days);
+            return;
+        };
+
+        let identifiers: Vec<String> = crate::cache::load_all_cached(&self.sources)
+            .iter()
+            .filter(|item| item.date < cutoff)
+            .map(|item| crate::seen::get_item_identifier(&item.link, &item.title))
+            .collect();
+        self.status = match self.mark_identifiers_seen(&identifiers) {
+            Ok(count) => format!("Marked {} items older than {} days as read", count, days),
+            Err(e) => format!("Failed to save seen status: {}", e),
+        };
+    }
+
+    pub fn cancel_mark_older(&mut self) {
+        self.mark_older_mode = false;
+        self.mark_older_input.clear();
+        self.status.clear();
+    }
+
+    // Looks through every feed's cache rather than the loaded list, so
+    // items outside the current view are included.
+    fn cached_identifiers_matching(&self, query: &Query) -> Vec<String> {
+        let mut items = crate::cache::load_all_cached(&self.sources);
+        for item in &mut items {
+            let identifier = crate::seen::get_item_identifier(&item.link, &item.title);
+            item.seen = self.seen_status.is_seen(&identifier);
+            item.liked = self.likes_status.is_liked(&identifier);
+        }
+        self.filter_items(&items, query)
+            .iter()
+            .map(|(_, item)| crate::seen::get_item_identifier(&item.link, &item.title))
+            .collect()
+    }
+
+    // Returns how many of the items were unread before.
+    fn mark_identifiers_seen(&mut self, identifiers: &[String]) -> anyhow::Result<usize> {
+        let mut count = 0;
+        for id in identifiers {
+            if !self.seen_status.is_seen(id) {
+                self.seen_status.mark_seen(id);
+                count += 1;
+            }
         }
+        let saved = self.seen_status.save();
+        self.sync_seen_flags();
+        saved.map(|_| count)
+    }
+
+    /// Shows every feed's cached items without fetching anything.
+    pub fn show_cached_items(&mut self) {
+        self.items = crate::cache::load_all_cached(&self.sources);
+        self.update_items_seen_status();
+        self.update_items_like_status();
+        self.loading = false;
+        self.item_index = 0;
+        self.item_list_state.select(Some(0));
     }
 
     pub fn update_items_like_status(&mut self) {
@@ -756,14 +1578,51 @@ impl App {
                 };
             }
             self.update_items_like_status();
+            self.recount_unread();
         }
     }
 
-    pub fn load_liked_items(&mut self) {
-        self.items = crate::cache::load_all_cached();
-        self.update_items_seen_status();
+    pub fn is_feed_failing(&self, source_name: &str) -> bool {
+        self.feed_health
+            .get(source_name)
+            .map(|h| h.is_failing())
+            .unwrap_or(false)
+    }
+
+    pub fn get_failing_feeds(&self) -> Vec<(&FeedSource, &FeedHealth)> {
+        self.sources
+            .iter()
+            .filter_map(|s| {
+                self.feed_health
+                    .get(&s.name)
+                    .filter(|h| h.is_failing())
+                    .map(|h| (s, h))
+            })
+            .collect()
+    }
+
+    pub fn toggle_health(&mut self) {
+        self.show_health = !self.show_health;
+    }
+
+    pub fn merge_feed_items(&mut self, source_name: &str, items: Vec<FeedItem>) {
+        self.items.retain(|item| item.source_name != source_name);
+        for item in &items {
+            let identifier = crate::seen::get_item_identifier(&item.link, &item.title);
+            if self.seen_status.is_seen(&identifier) {
+                self.hidden_read_items.insert(identifier);
+            }
+        }
+        self.items.extend(items);
+        self.items.sort_by_key(|item| std::cmp::Reverse(item.date));
+        self.sync_seen_flags();
         self.update_items_like_status();
-        self.item_index = 0;
-        self.item_list_state.select(Some(0));
+
+        let len = self.item_list_len();
+        if self.item_index >= len && len > 0 {
+            self.item_index = len - 1;
+            self.item_list_state.select(Some(self.item_index));
+        }
     }
+
 }
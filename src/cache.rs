@@ -1,5 +1,11 @@
-use crate::feed::FeedItem;
-use std::collections::HashMap;
+use crate::config::Retention;
+use crate::db;
+use crate::feed::{FeedItem, FeedSource, Validators};
+use crate::reader::Article;
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use rusqlite::Connection;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::PathBuf;
 
@@ -7,68 +13,270 @@ fn cache_dir() -> Option<PathBuf> {
     dirs::home_dir().map(|p| p.join(".miam"))
 }
 
-fn cache_file_path(feed_name: &str) -> Option<PathBuf> {
-    let safe_name: String = feed_name
-        .chars()
-        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
-        .collect();
-    cache_dir().map(|p| p.join(format!("{}.yml", safe_name)))
+pub fn feed_state_path(kind: &str, source: &FeedSource) -> Option<PathBuf> {
+    cache_dir().map(|p| p.join(kind).join(format!("{}.yml", source.id())))
 }
 
-pub fn ensure_cache_dir() {
-    if let Some(dir) = cache_dir() {
-        let _ = fs::create_dir_all(dir);
+pub fn load_cached_items(source: &FeedSource) -> Option<Vec<FeedItem>> {
+    let conn = db::open().ok()?;
+    let mut items = db::load_feed_items(&conn, &source.id()).ok()?;
+    if items.is_empty() {
+        return None;
     }
+    // The cache outlives renames, so the display name comes from the config
+    for item in &mut items {
+        item.source_name = source.name.clone();
+    }
+    Some(items)
+}
+
+// FNV-1a: stable across builds, unlike std's DefaultHasher
+pub fn stable_hash(value: &str) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in value.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+fn article_file_path(identifier: &str) -> Option<PathBuf> {
+    cache_dir().map(|p| p.join("articles").join(format!("{}.yml", stable_hash(identifier))))
 }
 
-pub fn load_cached_items(feed_name: &str) -> Option<Vec<FeedItem>> {
-    let path = cache_file_path(feed_name)?;
+pub fn has_article(identifier: &str) -> bool {
+    article_file_path(identifier).is_some_and(|p| p.exists())
+}
+
+pub fn load_article(identifier: &str) -> Option<Article> {
+    let path = article_file_path(identifier)?;
     let content = fs::read_to_string(path).ok()?;
     serde_yaml::from_str(&content).ok()
 }
 
-pub fn save_cached_items(feed_name: &str, items: &[FeedItem]) {
-    if let Some(path) = cache_file_path(feed_name) {
-        ensure_cache_dir();
-        if let Ok(content) = serde_yaml::to_string(items) {
+pub fn save_article(identifier: &str, article: &Article) {
+    if let Some(path) = article_file_path(identifier) {
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(content) = serde_yaml::to_string(article) {
             let _ = fs::write(path, content);
         }
     }
+    if let Ok(conn) = db::open() {
+        let _ = db::save_article_text(&conn, identifier, &article.content);
+    }
 }
 
-pub fn load_all_cached() -> Vec<FeedItem> {
-    let mut all_items = Vec::new();
-    if let Some(dir) = cache_dir() {
-        if let Ok(entries) = fs::read_dir(dir) {
-            for entry in entries.flatten() {
-                if entry.path().extension().map(|e| e == "yml").unwrap_or(false) {
-                    if let Ok(content) = fs::read_to_string(entry.path()) {
-                        if let Ok(items) = serde_yaml::from_str::<Vec<FeedItem>>(&content) {
-                            all_items.extend(items);
-                        }
-                    }
-                }
-            }
+pub fn load_validators(source: &FeedSource) -> Validators {
+    feed_state_path("http", source)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_yaml::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_validators(source: &FeedSource, validators: &Validators) {
+    if let Some(path) = feed_state_path("http", source) {
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(content) = serde_yaml::to_string(validators) {
+            let _ = fs::write(path, content);
         }
     }
-    all_items.sort_by(|a, b| b.date.cmp(&a.date));
-    dedup_items(&mut all_items);
-    all_items
 }
 
-pub fn merge_and_save(feed_name: &str, new_items: Vec<FeedItem>) -> Vec<FeedItem> {
-    let mut all_items = load_cached_items(feed_name).unwrap_or_default();
-    all_items.extend(new_items);
-    all_items.sort_by(|a, b| b.date.cmp(&a.date));
+pub fn forget_validators(source: &FeedSource) {
+    if let Some(path) = feed_state_path("http", source) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+pub fn load_all_cached(sources: &[FeedSource]) -> Vec<FeedItem> {
+    let names: HashMap<String, &str> = sources.iter().map(|s| (s.id(), s.name.as_str())).collect();
+    let rows = db::open()
+        .and_then(|conn| db::load_all_items(&conn))
+        .unwrap_or_default();
+
+    // Rows come back newest first; items of feeds no longer configured are skipped
+    let mut all_items: Vec<FeedItem> = rows
+        .into_iter()
+        .filter_map(|(feed_id, mut item)| {
+            item.source_name = names.get(&feed_id)?.to_string();
+            Some(item)
+        })
+        .collect();
     dedup_items(&mut all_items);
-    save_cached_items(feed_name, &all_items);
     all_items
 }
 
+const SEARCH_LIMIT: usize = 200;
+
+/// Searches titles, summaries and cached article text of every feed, best
+/// matches first. Every word must match, as a prefix.
+pub fn search(sources: &[FeedSource], query: &str) -> Vec<FeedItem> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|term| term.replace('"', ""))
+        .filter(|term| !term.is_empty())
+        .map(|term| format!("\"{}\"*", term))
+        .collect();
+    if terms.is_empty() {
+        return Vec::new();
+    }
+
+    let names: HashMap<String, &str> = sources.iter().map(|s| (s.id(), s.name.as_str())).collect();
+    let rows = db::open()
+        .and_then(|conn| db::search_items(&conn, &terms.join(" "), SEARCH_LIMIT))
+        .unwrap_or_default();
+    let mut results: Vec<FeedItem> = rows
+        .into_iter()
+        .filter_map(|(feed_id, mut item)| {
+            item.source_name = names.get(&feed_id)?.to_string();
+            Some(item)
+        })
+        .collect();
+    dedup_items(&mut results);
+    results
+}
+
+fn legacy_file_name(feed_name: &str) -> String {
+    feed_name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+/// Imports caches from the old name-keyed layout (`~/.miam/<name>.yml`) into
+/// the database. Names that sanitized to the same file shared a cache, so
+/// each feed only takes back the items it wrote.
+pub fn migrate_legacy_caches(sources: &[FeedSource]) {
+    let Some(dir) = cache_dir() else {
+        return;
+    };
+
+    let Ok(mut conn) = db::open() else {
+        return;
+    };
+
+    // A file shared by several feeds is kept if any of them failed to import it
+    let mut migrated = Vec::new();
+    let mut failed = HashSet::new();
+    for source in sources {
+        let file_name = format!("{}.yml", legacy_file_name(&source.name));
+        if file_name == "seen.yml" || file_name == "likes.yml" {
+            continue;
+        }
+        let legacy_path = dir.join(&file_name);
+        let Some(items) = db::read_legacy::<Vec<FeedItem>>(&legacy_path) else {
+            continue;
+        };
+
+        let own_items: Vec<FeedItem> = items
+            .into_iter()
+            .filter(|item| item.source_name == source.name)
+            .collect();
+        if db::upsert_items(&mut conn, &source.id(), &own_items).is_err() {
+            failed.insert(legacy_path);
+            continue;
+        }
+
+        if let Some(new_path) = feed_state_path("health", source) {
+            let legacy_health = dir.join("health").join(&file_name);
+            if legacy_health.exists() && !new_path.exists() {
+                let _ = fs::rename(legacy_health, new_path);
+            }
+        }
+        // Validators can't be split between colliding feeds; a full fetch rebuilds them
+        let _ = fs::remove_file(dir.join("http").join(&file_name));
+
+        migrated.push(legacy_path);
+    }
+
+    for path in migrated {
+        if !failed.contains(&path) {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Stores the fetched items and applies retention. Returns the feed's cached
+/// items and how many of them weren't cached before.
+pub fn merge_and_save(
+    source: &FeedSource,
+    new_items: Vec<FeedItem>,
+    retention: Retention,
+) -> (Vec<FeedItem>, usize) {
+    let mut added = HashSet::new();
+    if let Ok(mut conn) = db::open() {
+        added = db::upsert_items(&mut conn, &source.id(), &new_items).unwrap_or_default();
+        let _ = apply_retention(&conn, source, retention);
+    }
+    // Items retention dropped right away don't count as new
+    let merged = load_cached_items(source).unwrap_or(new_items);
+    let new_count = merged
+        .iter()
+        .filter(|item| added.contains(&db::item_key(item)))
+        .count();
+    (merged, new_count)
+}
+
+fn apply_retention(conn: &Connection, source: &FeedSource, global: Retention) -> Result<usize> {
+    let retention = source.retention(global);
+    // An age reaching past the dates chrono can hold keeps everything
+    let oldest = retention
+        .max_age_days
+        .and_then(|days| Utc::now().checked_sub_signed(Duration::days(i64::from(days))));
+    db::prune_feed(conn, &source.id(), retention.max_items, oldest)
+}
+
+pub struct PruneStats {
+    pub items: usize,
+    pub articles: usize,
+    pub seen: usize,
+}
+
+/// Applies the retention policy to every feed, drops items of feeds that are
+/// no longer configured and the saved articles and read markers of items
+/// that are gone, then compacts the database.
+pub fn prune(sources: &[FeedSource], retention: Retention) -> Result<PruneStats> {
+    let conn = db::open()?;
+    let mut items = 0;
+    for source in sources {
+        items += apply_retention(&conn, source, retention)?;
+    }
+    let feed_ids: Vec<String> = sources.iter().map(|s| s.id()).collect();
+    items += db::prune_unknown_feeds(&conn, &feed_ids)?;
+    let seen = db::prune_seen(&conn)?;
+    db::prune_article_text(&conn)?;
+    let articles = prune_articles(&db::item_keys(&conn)?);
+    db::vacuum(&conn)?;
+    Ok(PruneStats { items, articles, seen })
+}
+
+// Article files are named by a hash of the item key, so the keys still cached
+// are hashed to tell which files to keep.
+fn prune_articles(keys: &HashSet<String>) -> usize {
+    let Some(dir) = cache_dir().map(|p| p.join("articles")) else {
+        return 0;
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    let kept: HashSet<String> = keys.iter().map(|key| format!("{}.yml", stable_hash(key))).collect();
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !kept.contains(&name) && fs::remove_file(entry.path()).is_ok() {
+            removed += 1;
+        }
+    }
+    removed
+}
+
 fn dedup_items(items: &mut Vec<FeedItem>) {
     let mut seen: HashMap<String, ()> = HashMap::new();
     items.retain(|item| {
-        let key = item.link.clone().unwrap_or_else(|| item.title.clone());
-        seen.insert(key, ()).is_none()
+        seen.insert(db::item_key(item), ()).is_none()
     });
 }
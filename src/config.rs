@@ -12,7 +12,7 @@ const INVIDIOUS_INSTANCES: &[&str] = &[
     "iv.melmac.space",
 ];
 
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize)]
 pub struct Settings {
     #[serde(default)]
     pub invidious: bool,
@@ -26,6 +26,40 @@ pub struct Settings {
     pub browser_command: Option<String>,
     #[serde(default = "default_hide_shorts")]
     pub hide_shorts: bool,
+    #[serde(default = "default_refresh_concurrency")]
+    pub refresh_concurrency: usize,
+    #[serde(default)]
+    pub max_items_per_feed: Option<usize>,
+    #[serde(default)]
+    pub max_age_days: Option<u32>,
+    #[serde(default)]
+    pub image_protocol: ImageProtocol,
+}
+
+impl Settings {
+    pub fn retention(&self) -> Retention {
+        Retention {
+            max_items: self.max_items_per_feed,
+            max_age_days: self.max_age_days,
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            invidious: false,
+            invidious_instance: None,
+            show_tooltips: default_show_tooltips(),
+            paywall_remover: default_paywall_remover(),
+            browser_command: None,
+            hide_shorts: default_hide_shorts(),
+            refresh_concurrency: default_refresh_concurrency(),
+            max_items_per_feed: None,
+            max_age_days: None,
+            image_protocol: ImageProtocol::default(),
+        }
+    }
 }
 
 fn default_show_tooltips() -> bool {
@@ -40,16 +74,83 @@ fn default_hide_shorts() -> bool {
     false
 }
 
+fn default_refresh_concurrency() -> usize {
+    8
+}
+
+/// How much of a feed's history is kept in the cache. Liked items are
+/// always kept.
+#[derive(Clone, Copy, Default)]
+pub struct Retention {
+    pub max_items: Option<usize>,
+    pub max_age_days: Option<u32>,
+}
+
+/// How the reader draws images. `Auto` picks what the terminal supports.
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageProtocol {
+    #[default]
+    Auto,
+    Kitty,
+    Iterm2,
+    Sixel,
+    Halfblocks,
+    None,
+}
+
+#[derive(Default)]
 pub struct Config {
     pub sources: Vec<FeedSource>,
     pub settings: Settings,
+    /// Named queries shown as virtual feeds in the sidebar.
+    pub saved_searches: IndexMap<String, String>,
 }
 
 #[derive(Serialize, Deserialize)]
 #[serde(untagged)]
 enum FeedEntry {
     Simple(String),
-    WithTags { url: String, tags: Vec<String> },
+    WithTags {
+        url: String,
+        #[serde(default)]
+        tags: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_items: Option<usize>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_age_days: Option<u32>,
+    },
+}
+
+impl FeedEntry {
+    fn into_source(self, name: String) -> FeedSource {
+        match self {
+            FeedEntry::Simple(url) => FeedSource {
+                name,
+                url,
+                tags: Vec::new(),
+                id: None,
+                max_items: None,
+                max_age_days: None,
+            },
+            FeedEntry::WithTags {
+                url,
+                tags,
+                id,
+                max_items,
+                max_age_days,
+            } => FeedSource {
+                name,
+                url,
+                tags,
+                id,
+                max_items,
+                max_age_days,
+            },
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -68,6 +169,16 @@ struct ConfigFile {
     browser_command: Option<String>,
     #[serde(default = "default_hide_shorts")]
     hide_shorts: bool,
+    #[serde(default = "default_refresh_concurrency")]
+    refresh_concurrency: usize,
+    #[serde(default)]
+    max_items_per_feed: Option<usize>,
+    #[serde(default)]
+    max_age_days: Option<u32>,
+    #[serde(default)]
+    image_protocol: ImageProtocol,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    saved_searches: IndexMap<String, String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -83,6 +194,10 @@ impl Config {
         dirs::home_dir().map(|p| p.join(".miam.yml"))
     }
 
+    pub fn exists() -> bool {
+        Self::config_path().map(|p| p.exists()).unwrap_or(false)
+    }
+
     pub fn load() -> Option<Self> {
         let path = Self::config_path()?;
         let content = fs::read_to_string(path).ok()?;
@@ -92,14 +207,7 @@ impl Config {
             let sources = config_file
                 .feeds
                 .into_iter()
-                .map(|(name, entry)| match entry {
-                    FeedEntry::Simple(url) => FeedSource {
-                        name,
-                        url,
-                        tags: Vec::new(),
-                    },
-                    FeedEntry::WithTags { url, tags } => FeedSource { name, url, tags },
-                })
+                .map(|(name, entry)| entry.into_source(name))
                 .collect();
             return Some(Config {
                 sources,
@@ -110,7 +218,12 @@ impl Config {
                     paywall_remover: config_file.paywall_remover,
                     browser_command: config_file.browser_command,
                     hide_shorts: config_file.hide_shorts,
+                    refresh_concurrency: config_file.refresh_concurrency,
+                    max_items_per_feed: config_file.max_items_per_feed,
+                    max_age_days: config_file.max_age_days,
+                    image_protocol: config_file.image_protocol,
                 },
+                saved_searches: config_file.saved_searches,
             });
         }
 
@@ -119,18 +232,12 @@ impl Config {
             let sources = old_config
                 .feeds
                 .into_iter()
-                .map(|(name, entry)| match entry {
-                    FeedEntry::Simple(url) => FeedSource {
-                        name,
-                        url,
-                        tags: Vec::new(),
-                    },
-                    FeedEntry::WithTags { url, tags } => FeedSource { name, url, tags },
-                })
+                .map(|(name, entry)| entry.into_source(name))
                 .collect();
             return Some(Config {
                 sources,
                 settings: old_config.settings,
+                saved_searches: IndexMap::new(),
             });
         }
 
@@ -138,15 +245,12 @@ impl Config {
         let map: IndexMap<String, String> = serde_yaml::from_str(&content).ok()?;
         let sources = map
             .into_iter()
-            .map(|(name, url)| FeedSource {
-                name,
-                url,
-                tags: Vec::new(),
-            })
+            .map(|(name, url)| FeedEntry::Simple(url).into_source(name))
             .collect();
         Some(Config {
             sources,
             settings: Settings::default(),
+            saved_searches: IndexMap::new(),
         })
     }
 
@@ -156,12 +260,19 @@ impl Config {
                 .sources
                 .iter()
                 .map(|s| {
-                    let entry = if s.tags.is_empty() {
+                    let entry = if s.tags.is_empty()
+                        && s.id.is_none()
+                        && s.max_items.is_none()
+                        && s.max_age_days.is_none()
+                    {
                         FeedEntry::Simple(s.url.clone())
                     } else {
                         FeedEntry::WithTags {
                             url: s.url.clone(),
                             tags: s.tags.clone(),
+                            id: s.id.clone(),
+                            max_items: s.max_items,
+                            max_age_days: s.max_age_days,
                         }
                     };
                     (s.name.clone(), entry)
@@ -175,6 +286,11 @@ impl Config {
                 paywall_remover: self.settings.paywall_remover,
                 browser_command: self.settings.browser_command.clone(),
                 hide_shorts: self.settings.hide_shorts,
+                refresh_concurrency: self.settings.refresh_concurrency,
+                max_items_per_feed: self.settings.max_items_per_feed,
+                max_age_days: self.settings.max_age_days,
+                image_protocol: self.settings.image_protocol,
+                saved_searches: self.saved_searches.clone(),
             };
             if let Ok(content) = serde_yaml::to_string(&config_file) {
                 let _ = fs::write(path, content);
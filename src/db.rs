@@ -0,0 +1,443 @@
+use crate::feed::{FeedItem, SNIPPET_END, SNIPPET_START};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, Row};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+const SCHEMA_VERSION: u32 = 2;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS items (
+    feed_id TEXT NOT NULL,
+    item_key TEXT NOT NULL,
+    title TEXT NOT NULL,
+    link TEXT,
+    date INTEGER NOT NULL,
+    source_name TEXT NOT NULL,
+    is_youtube_short INTEGER NOT NULL DEFAULT 0,
+    summary TEXT,
+    content TEXT,
+    author TEXT,
+    categories TEXT NOT NULL DEFAULT '[]',
+    guid TEXT,
+    PRIMARY KEY (feed_id, item_key)
+);
+CREATE INDEX IF NOT EXISTS items_date ON items (date DESC);
+CREATE INDEX IF NOT EXISTS items_feed_date ON items (feed_id, date DESC);
+CREATE TABLE IF NOT EXISTS seen (identifier TEXT PRIMARY KEY);
+CREATE TABLE IF NOT EXISTS likes (identifier TEXT PRIMARY KEY);
+";
+
+// Full-text index over items, kept in sync by triggers. Rows share the rowid
+// of the item they index. Article text is filled in when an article is cached.
+const SEARCH_SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS article_text (identifier TEXT PRIMARY KEY, text TEXT NOT NULL);
+CREATE VIRTUAL TABLE IF NOT EXISTS item_search USING fts5(
+    title, body, article,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+CREATE TRIGGER IF NOT EXISTS items_search_insert AFTER INSERT ON items BEGIN
+    INSERT INTO item_search (rowid, title, body, article) VALUES (
+        new.rowid, new.title, coalesce(new.summary, ''),
+        coalesce((SELECT text FROM article_text WHERE identifier = new.item_key), ''));
+END;
+CREATE TRIGGER IF NOT EXISTS items_search_update AFTER UPDATE ON items BEGIN
+    DELETE FROM item_search WHERE rowid = old.rowid;
+    INSERT INTO item_search (rowid, title, body, article) VALUES (
+        new.rowid, new.title, coalesce(new.summary, ''),
+        coalesce((SELECT text FROM article_text WHERE identifier = new.item_key), ''));
+END;
+CREATE TRIGGER IF NOT EXISTS items_search_delete AFTER DELETE ON items BEGIN
+    DELETE FROM item_search WHERE rowid = old.rowid;
+END;
+CREATE TRIGGER IF NOT EXISTS article_text_upsert AFTER INSERT ON article_text BEGIN
+    UPDATE item_search SET article = new.text
+    WHERE rowid IN (SELECT rowid FROM items WHERE item_key = new.identifier);
+END;
+DELETE FROM item_search;
+INSERT INTO item_search (rowid, title, body, article)
+    SELECT rowid, title, coalesce(summary, ''), '' FROM items;
+";
+
+const ITEM_COLUMNS: &str = "title, link, date, source_name, is_youtube_short, summary, content, author, categories, guid";
+
+fn miam_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|p| p.join(".miam"))
+}
+
+fn db_path() -> Result<PathBuf> {
+    miam_dir()
+        .map(|p| p.join("miam.db"))
+        .context("Could not find home directory")
+}
+
+/// Opens the database, creating the schema and importing the old YAML
+/// storage the first time it is opened in this process.
+pub fn open() -> Result<Connection> {
+    static INITIALIZED: Mutex<bool> = Mutex::new(false);
+
+    let path = db_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut conn = Connection::open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    // Feeds are refreshed from several threads, each with its own connection
+    conn.busy_timeout(Duration::from_secs(10))?;
+
+    let mut initialized = INITIALIZED.lock().unwrap_or_else(|e| e.into_inner());
+    if !*initialized {
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        migrate(&conn)?;
+        import_yaml(&mut conn);
+        *initialized = true;
+    }
+    drop(initialized);
+    Ok(conn)
+}
+
+fn migrate(conn: &Connection) -> Result<()> {
+    let version: u32 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if version >= SCHEMA_VERSION {
+        return Ok(());
+    }
+    if version < 1 {
+        conn.execute_batch(SCHEMA)?;
+    }
+    if version < 2 {
+        conn.execute_batch(SEARCH_SCHEMA)?;
+        index_cached_articles(conn)?;
+    }
+    conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+    Ok(())
+}
+
+// Articles cached before the search index existed only live on disk
+fn index_cached_articles(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare("SELECT DISTINCT item_key FROM items")?;
+    let keys: Vec<String> = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    for key in keys {
+        if let Some(article) = crate::cache::load_article(&key) {
+            save_article_text(conn, &key, &article.content)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn save_article_text(conn: &Connection, identifier: &str, text: &str) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO article_text (identifier, text) VALUES (?1, ?2)",
+        params![identifier, text],
+    )?;
+    Ok(())
+}
+
+/// Runs a full-text query, best matches first. Returns the feed id of each
+/// item along with the item, whose `snippet` holds the highlighted match.
+pub fn search_items(conn: &Connection, query: &str, limit: usize) -> Result<Vec<(String, FeedItem)>> {
+    let columns: Vec<String> = ITEM_COLUMNS
+        .split(", ")
+        .map(|c| format!("items.{}", c))
+        .collect();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}, items.feed_id, snippet(item_search, -1, ?2, ?3, '…', 16)
+         FROM item_search JOIN items ON items.rowid = item_search.rowid
+         WHERE item_search MATCH ?1
+         ORDER BY bm25(item_search, 10.0, 3.0, 1.0)
+         LIMIT ?4",
+        columns.join(", ")
+    ))?;
+    let items = stmt
+        .query_map(
+            params![
+                query,
+                SNIPPET_START.to_string(),
+                SNIPPET_END.to_string(),
+                limit as i64
+            ],
+            |row| {
+                let mut item = item_from_row(row)?;
+                item.snippet = row.get(11)?;
+                Ok((row.get(10)?, item))
+            },
+        )?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(items)
+}
+
+pub fn item_key(item: &FeedItem) -> String {
+    item.link.clone().unwrap_or_else(|| item.title.clone())
+}
+
+fn item_from_row(row: &Row) -> rusqlite::Result<FeedItem> {
+    let categories: String = row.get(8)?;
+    Ok(FeedItem {
+        title: row.get(0)?,
+        link: row.get(1)?,
+        date: DateTime::from_timestamp_millis(row.get(2)?).unwrap_or_default(),
+        source_name: row.get(3)?,
+        is_youtube_short: row.get(4)?,
+        summary: row.get(5)?,
+        content: row.get(6)?,
+        author: row.get(7)?,
+        categories: serde_json::from_str(&categories).unwrap_or_default(),
+        guid: row.get(9)?,
+        seen: false,
+        liked: false,
+        snippet: None,
+    })
+}
+
+pub fn load_feed_items(conn: &Connection, feed_id: &str) -> Result<Vec<FeedItem>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM items WHERE feed_id = ?1 ORDER BY date DESC",
+        ITEM_COLUMNS
+    ))?;
+    let items = stmt
+        .query_map([feed_id], item_from_row)?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(items)
+}
+
+pub fn load_all_items(conn: &Connection) -> Result<Vec<(String, FeedItem)>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}, feed_id FROM items ORDER BY date DESC",
+        ITEM_COLUMNS
+    ))?;
+    let items = stmt
+        .query_map([], |row| Ok((row.get(10)?, item_from_row(row)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(items)
+}
+
+/// Inserts or updates the feed's items. Returns the keys of the items that
+/// weren't stored before.
+pub fn upsert_items(conn: &mut Connection, feed_id: &str, items: &[FeedItem]) -> Result<HashSet<String>> {
+    let mut added = HashSet::new();
+    let tx = conn.transaction()?;
+    {
+        let mut exists = tx.prepare("SELECT 1 FROM items WHERE feed_id = ?1 AND item_key = ?2")?;
+        let mut stmt = tx.prepare(&format!(
+            "INSERT INTO items (feed_id, item_key, {})
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+             ON CONFLICT (feed_id, item_key) DO UPDATE SET
+                title = excluded.title,
+                link = excluded.link,
+                date = excluded.date,
+                source_name = excluded.source_name,
+                is_youtube_short = excluded.is_youtube_short,
+                summary = excluded.summary,
+                content = excluded.content,
+                author = excluded.author,
+                categories = excluded.categories,
+                guid = excluded.guid",
+            ITEM_COLUMNS
+        ))?;
+        for item in items {
+            let key = item_key(item);
+            if !exists.exists(params![feed_id, key])? {
+                added.insert(key.clone());
+            }
+            stmt.execute(params![
+                feed_id,
+                key,
+                item.title,
+                item.link,
+                item.date.timestamp_millis(),
+                item.source_name,
+                item.is_youtube_short,
+                item.summary,
+                item.content,
+                item.author,
+                serde_json::to_string(&item.categories)?,
+                item.guid,
+            ])?;
+        }
+    }
+    tx.commit()?;
+    Ok(added)
+}
+
+/// Deletes the feed's items beyond the newest `max_items` or older than
+/// `oldest`, sparing liked items. Returns the number of items removed.
+pub fn prune_feed(
+    conn: &Connection,
+    feed_id: &str,
+    max_items: Option<usize>,
+    oldest: Option<DateTime<Utc>>,
+) -> Result<usize> {
+    // LIMIT -1 means no limit in SQLite
+    let limit = max_items.map(|n| n as i64).unwrap_or(-1);
+    let cutoff = oldest.map(|d| d.timestamp_millis()).unwrap_or(i64::MIN);
+    let removed = conn.execute(
+        "DELETE FROM items
+         WHERE feed_id = ?1
+           AND item_key NOT IN (SELECT identifier FROM likes)
+           AND (date < ?3 OR item_key NOT IN (
+                SELECT item_key FROM items WHERE feed_id = ?1 ORDER BY date DESC LIMIT ?2))",
+        params![feed_id, limit, cutoff],
+    )?;
+    Ok(removed)
+}
+
+/// Deletes items of feeds that are no longer configured, sparing liked items.
+pub fn prune_unknown_feeds(conn: &Connection, feed_ids: &[String]) -> Result<usize> {
+    let placeholders = vec!["?"; feed_ids.len()].join(", ");
+    let removed = conn.execute(
+        &format!(
+            "DELETE FROM items
+             WHERE feed_id NOT IN ({})
+               AND item_key NOT IN (SELECT identifier FROM likes)",
+            placeholders
+        ),
+        rusqlite::params_from_iter(feed_ids),
+    )?;
+    Ok(removed)
+}
+
+/// Forgets read markers for items that are no longer cached.
+pub fn prune_seen(conn: &Connection) -> Result<usize> {
+    let removed = conn.execute(
+        "DELETE FROM seen WHERE identifier NOT IN (SELECT item_key FROM items)",
+        [],
+    )?;
+    Ok(removed)
+}
+
+/// Forgets the text of articles whose item is no longer cached.
+pub fn prune_article_text(conn: &Connection) -> Result<usize> {
+    let removed = conn.execute(
+        "DELETE FROM article_text WHERE identifier NOT IN (SELECT item_key FROM items)",
+        [],
+    )?;
+    Ok(removed)
+}
+
+pub fn item_keys(conn: &Connection) -> Result<HashSet<String>> {
+    let mut stmt = conn.prepare("SELECT DISTINCT item_key FROM items")?;
+    let keys = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<HashSet<String>>>()?;
+    Ok(keys)
+}
+
+pub fn vacuum(conn: &Connection) -> Result<()> {
+    conn.execute_batch("VACUUM")?;
+    Ok(())
+}
+
+pub fn load_set(conn: &Connection, table: &str) -> Result<HashSet<String>> {
+    let mut stmt = conn.prepare(&format!("SELECT identifier FROM {}", table))?;
+    let set = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(set)
+}
+
+pub fn update_set(conn: &mut Connection, table: &str, changes: &[(String, bool)]) -> Result<()> {
+    let tx = conn.transaction()?;
+    {
+        let mut insert = tx.prepare(&format!(
+            "INSERT OR IGNORE INTO {} (identifier) VALUES (?1)",
+            table
+        ))?;
+        let mut delete = tx.prepare(&format!("DELETE FROM {} WHERE identifier = ?1", table))?;
+        for (identifier, present) in changes {
+            if *present {
+                insert.execute([identifier])?;
+            } else {
+                delete.execute([identifier])?;
+            }
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct YamlSeen {
+    #[serde(default)]
+    seen_items: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct YamlLikes {
+    #[serde(default)]
+    liked_items: Vec<String>,
+}
+
+// One-time import of the YAML storage used before the database existed:
+// per-feed caches in ~/.miam/feeds/<id>.yml plus seen.yml and likes.yml.
+// Imported files are moved out of the way so this only ever runs once. A
+// file that can't be written to the database is left for the next run.
+fn import_yaml(conn: &mut Connection) {
+    let Some(dir) = miam_dir() else {
+        return;
+    };
+
+    let feeds_dir = dir.join("feeds");
+    if let Ok(entries) = fs::read_dir(&feeds_dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_none_or(|e| e != "yml") {
+                continue;
+            }
+            let Some(feed_id) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let Some(items) = read_legacy::<Vec<FeedItem>>(&path) else {
+                continue;
+            };
+            if upsert_items(conn, feed_id, &items).is_ok() {
+                let _ = fs::remove_file(&path);
+            }
+        }
+        let _ = fs::remove_dir(&feeds_dir);
+    }
+
+    let seen_path = dir.join("seen.yml");
+    if let Some(seen) = read_legacy::<YamlSeen>(&seen_path) {
+        let changes: Vec<_> = seen.seen_items.into_iter().map(|id| (id, true)).collect();
+        if update_set(conn, "seen", &changes).is_ok() {
+            let _ = fs::rename(&seen_path, dir.join("seen.yml.imported"));
+        }
+    }
+
+    let likes_path = dir.join("likes.yml");
+    if let Some(likes) = read_legacy::<YamlLikes>(&likes_path) {
+        let changes: Vec<_> = likes.liked_items.into_iter().map(|id| (id, true)).collect();
+        if update_set(conn, "likes", &changes).is_ok() {
+            let _ = fs::rename(&likes_path, dir.join("likes.yml.imported"));
+        }
+    }
+}
+
+/// Reads a file of the old YAML storage. One that doesn't parse is moved to
+/// `<name>.bad` with a warning, so it isn't read again and the rest of the
+/// import goes on without it.
+pub fn read_legacy<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let content = fs::read_to_string(path).ok()?;
+    match serde_yaml::from_str(&content) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            let mut bad = path.as_os_str().to_owned();
+            bad.push(".bad");
+            eprintln!(
+                "Warning: could not import {}: {}. Moved it to {}",
+                path.display(),
+                e,
+                Path::new(&bad).display()
+            );
+            let _ = fs::rename(path, &bad);
+            None
+        }
+    }
+}
@@ -0,0 +1,598 @@
+use scraper::{ElementRef, Html, Node};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+use textwrap::core::display_width;
+use url::Url;
+
+/// A block of an article, as parsed from its HTML.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Block {
+    Heading { level: u8, text: Vec<Inline> },
+    Paragraph(Vec<Inline>),
+    List { ordered: bool, start: usize, items: Vec<Vec<Block>> },
+    Quote(Vec<Block>),
+    Code { language: Option<String>, text: String },
+    Table {
+        header: bool,
+        #[serde(deserialize_with = "deserialize_rows")]
+        rows: Vec<Vec<Vec<Inline>>>,
+    },
+    Image { src: String, alt: String },
+    Rule,
+}
+
+/// A run of text sharing one style. `\n` inside `text` is a hard line break.
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Inline {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub bold: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub italic: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub code: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+// Articles cached before table cells kept their links have plain text cells.
+fn deserialize_rows<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Vec<Vec<Inline>>>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Cell {
+        Text(String),
+        Inlines(Vec<Inline>),
+    }
+    let rows: Vec<Vec<Cell>> = Vec::deserialize(deserializer)?;
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            row.into_iter()
+                .map(|cell| match cell {
+                    Cell::Text(text) => vec![Inline { text, ..Inline::default() }],
+                    Cell::Inlines(inlines) => inlines,
+                })
+                .collect()
+        })
+        .collect())
+}
+
+impl Inline {
+    fn same_style(&self, other: &Inline) -> bool {
+        self.bold == other.bold
+            && self.italic == other.italic
+            && self.code == other.code
+            && self.link == other.link
+    }
+}
+
+/// Parses an HTML fragment into blocks. Relative links and image sources
+/// are resolved against `base` when given.
+pub fn parse(html: &str, base: Option<&Url>) -> Vec<Block> {
+    let fragment = Html::parse_fragment(html);
+    let parser = Parser { base };
+    parser.blocks_of(fragment.root_element(), &Inline::default())
+}
+
+/// Splits plain text into paragraphs, for articles cached before they were
+/// stored as blocks.
+pub fn from_text(text: &str) -> Vec<Block> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            Block::Paragraph(vec![Inline {
+                text: line.to_string(),
+                ..Inline::default()
+            }])
+        })
+        .collect()
+}
+
+/// The blocks as plain text, one paragraph per line, for search and for
+/// places that can't show styles.
+pub fn to_text(blocks: &[Block]) -> String {
+    let mut out = Vec::new();
+    for block in blocks {
+        match block {
+            Block::Heading { text, .. } | Block::Paragraph(text) => out.push(inline_text(text)),
+            Block::List { items, .. } => {
+                for item in items {
+                    out.push(format!("• {}", to_text(item).replace('\n', " ")));
+                }
+            }
+            Block::Quote(inner) => out.push(to_text(inner)),
+            Block::Code { text, .. } => out.push(text.clone()),
+            Block::Table { rows, .. } => {
+                out.extend(rows.iter().map(|row| {
+                    row.iter().map(|cell| inline_text(cell)).collect::<Vec<_>>().join(" | ")
+                }));
+            }
+            Block::Image { alt, .. } => {
+                if !alt.is_empty() {
+                    out.push(alt.clone());
+                }
+            }
+            Block::Rule => {}
+        }
+    }
+    out.join("\n")
+}
+
+/// Every link target in the order it first appears, without repeats. A
+/// link's number in the reader is its position here plus one.
+pub fn links(blocks: &[Block]) -> Vec<String> {
+    let mut links = Vec::new();
+    collect_links(blocks, &mut links);
+    links
+}
+
+fn collect_links(blocks: &[Block], links: &mut Vec<String>) {
+    for block in blocks {
+        match block {
+            Block::Heading { text, .. } | Block::Paragraph(text) => collect_inline_links(text, links),
+            Block::Table { rows, .. } => {
+                for cell in rows.iter().flatten() {
+                    collect_inline_links(cell, links);
+                }
+            }
+            Block::List { items, .. } => {
+                for item in items {
+                    collect_links(item, links);
+                }
+            }
+            Block::Quote(inner) => collect_links(inner, links),
+            _ => {}
+        }
+    }
+}
+
+fn collect_inline_links(inlines: &[Inline], links: &mut Vec<String>) {
+    for link in inlines.iter().filter_map(|inline| inline.link.as_ref()) {
+        if !links.contains(link) {
+            links.push(link.clone());
+        }
+    }
+}
+
+/// Gives code blocks back the languages marked in the page they were
+/// extracted from. Extraction drops class attributes, so blocks are matched
+/// up by their text.
+pub fn restore_code_languages(blocks: &mut [Block], page: &str) {
+    let page = Html::parse_document(page);
+    let languages: HashMap<String, String> = page
+        .root_element()
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .filter(|element| element.value().name() == "pre")
+        .filter_map(|pre| Some((code_text(pre), code_language(pre)?)))
+        .collect();
+    if !languages.is_empty() {
+        set_code_languages(blocks, &languages);
+    }
+}
+
+fn set_code_languages(blocks: &mut [Block], languages: &HashMap<String, String>) {
+    for block in blocks {
+        match block {
+            Block::Code { language: language @ None, text } => {
+                *language = languages.get(text.as_str()).cloned();
+            }
+            Block::List { items, .. } => {
+                for item in items {
+                    set_code_languages(item, languages);
+                }
+            }
+            Block::Quote(inner) => set_code_languages(inner, languages),
+            _ => {}
+        }
+    }
+}
+
+/// The sources of the images in the blocks, in order.
+pub fn images(blocks: &[Block]) -> Vec<String> {
+    let mut sources = Vec::new();
+    for block in blocks {
+        match block {
+            Block::Image { src, .. } => sources.push(src.clone()),
+            Block::List { items, .. } => sources.extend(items.iter().flat_map(|item| images(item))),
+            Block::Quote(inner) => sources.extend(images(inner)),
+            _ => {}
+        }
+    }
+    sources
+}
+
+/// The width of the widest line of code in the blocks.
+pub fn code_width(blocks: &[Block]) -> usize {
+    blocks
+        .iter()
+        .map(|block| match block {
+            Block::Code { text, .. } => text.lines().map(display_width).max().unwrap_or(0),
+            Block::List { items, .. } => items.iter().map(|item| code_width(item)).max().unwrap_or(0),
+            Block::Quote(inner) => code_width(inner),
+            _ => 0,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+pub fn inline_text(inlines: &[Inline]) -> String {
+    inlines.iter().map(|i| i.text.as_str()).collect()
+}
+
+struct Parser<'a> {
+    base: Option<&'a Url>,
+}
+
+impl Parser<'_> {
+    fn blocks_of(&self, element: ElementRef, style: &Inline) -> Vec<Block> {
+        let mut blocks = Vec::new();
+        let mut inlines = Vec::new();
+        self.walk(element, style, &mut inlines, &mut blocks);
+        flush(&mut inlines, &mut blocks);
+        blocks
+    }
+
+    // Inline content collects in `inlines` until a block element ends the
+    // paragraph it belongs to.
+    fn walk(&self, element: ElementRef, style: &Inline, inlines: &mut Vec<Inline>, blocks: &mut Vec<Block>) {
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => push_text(inlines, text, style),
+                Node::Element(_) => {
+                    if let Some(child) = ElementRef::wrap(child) {
+                        self.element(child, style, inlines, blocks);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn element(&self, element: ElementRef, style: &Inline, inlines: &mut Vec<Inline>, blocks: &mut Vec<Block>) {
+        let name = element.value().name();
+        match name {
+            "script" | "style" | "noscript" | "template" | "iframe" | "svg" | "button" | "form"
+            | "nav" | "head" => {}
+            "br" => push_break(inlines, style),
+            "strong" | "b" => {
+                let style = Inline { bold: true, ..style.clone() };
+                self.walk(element, &style, inlines, blocks);
+            }
+            "em" | "i" | "cite" | "dfn" => {
+                let style = Inline { italic: true, ..style.clone() };
+                self.walk(element, &style, inlines, blocks);
+            }
+            "code" | "kbd" | "samp" | "tt" => {
+                let style = Inline { code: true, ..style.clone() };
+                self.walk(element, &style, inlines, blocks);
+            }
+            "a" => {
+                let link = element
+                    .value()
+                    .attr("href")
+                    .filter(|href| !href.starts_with('#') && !href.starts_with("javascript:"))
+                    .map(|href| self.resolve(href));
+                let style = Inline { link: link.or(style.link.clone()), ..style.clone() };
+                self.walk(element, &style, inlines, blocks);
+            }
+            "img" => {
+                let src = element
+                    .value()
+                    .attr("src")
+                    .or_else(|| element.value().attr("data-src"));
+                if let Some(src) = src {
+                    flush(inlines, blocks);
+                    blocks.push(Block::Image {
+                        src: self.resolve(src),
+                        alt: element.value().attr("alt").unwrap_or_default().trim().to_string(),
+                    });
+                }
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                flush(inlines, blocks);
+                let level = name[1..].parse().unwrap_or(1);
+                let mut text = Vec::new();
+                let mut nested = Vec::new();
+                self.walk(element, style, &mut text, &mut nested);
+                trim(&mut text);
+                if !text.is_empty() {
+                    blocks.push(Block::Heading { level, text });
+                }
+                blocks.extend(nested);
+            }
+            "ul" | "ol" | "menu" => {
+                flush(inlines, blocks);
+                let start = element
+                    .value()
+                    .attr("start")
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(1);
+                let items: Vec<Vec<Block>> = element
+                    .child_elements()
+                    .filter(|child| child.value().name() == "li")
+                    .map(|li| self.blocks_of(li, style))
+                    .collect();
+                if !items.is_empty() {
+                    blocks.push(Block::List { ordered: name == "ol", start, items });
+                }
+            }
+            "blockquote" => {
+                flush(inlines, blocks);
+                let inner = self.blocks_of(element, style);
+                if !inner.is_empty() {
+                    blocks.push(Block::Quote(inner));
+                }
+            }
+            "pre" => {
+                flush(inlines, blocks);
+                let text = code_text(element);
+                if !text.is_empty() {
+                    blocks.push(Block::Code { language: code_language(element), text });
+                }
+            }
+            "table" => {
+                flush(inlines, blocks);
+                if let Some(table) = self.table(element) {
+                    blocks.push(table);
+                }
+            }
+            "hr" => {
+                flush(inlines, blocks);
+                blocks.push(Block::Rule);
+            }
+            "p" | "div" | "section" | "article" | "main" | "header" | "footer" | "aside"
+            | "figure" | "figcaption" | "picture" | "dl" | "dt" | "dd" | "details" | "summary"
+            | "address" | "center" | "li" | "body" | "html" => {
+                flush(inlines, blocks);
+                self.walk(element, style, inlines, blocks);
+                flush(inlines, blocks);
+            }
+            _ => self.walk(element, style, inlines, blocks),
+        }
+    }
+
+    fn table(&self, element: ElementRef) -> Option<Block> {
+        let mut header = false;
+        let mut rows = Vec::new();
+        for row in element.descendants().filter_map(ElementRef::wrap) {
+            if row.value().name() != "tr" {
+                continue;
+            }
+            let cells: Vec<ElementRef> = row
+                .child_elements()
+                .filter(|cell| matches!(cell.value().name(), "td" | "th"))
+                .collect();
+            if cells.is_empty() {
+                continue;
+            }
+            if rows.is_empty() {
+                header = cells.iter().all(|cell| cell.value().name() == "th");
+            }
+            rows.push(cells.into_iter().map(|cell| self.cell(cell)).collect());
+        }
+        (!rows.is_empty()).then_some(Block::Table { header, rows })
+    }
+
+    // A cell is drawn on one line, so its paragraphs are joined up. Only text
+    // and links are kept.
+    fn cell(&self, cell: ElementRef) -> Vec<Inline> {
+        let mut inlines: Vec<Inline> = Vec::new();
+        for block in self.blocks_of(cell, &Inline::default()) {
+            let text = match block {
+                Block::Heading { text, .. } | Block::Paragraph(text) => text,
+                other => vec![Inline {
+                    text: to_text(std::slice::from_ref(&other)),
+                    ..Inline::default()
+                }],
+            };
+            if !inlines.is_empty() {
+                inlines.push(Inline { text: " ".to_string(), ..Inline::default() });
+            }
+            inlines.extend(text);
+        }
+        for inline in &mut inlines {
+            inline.text = inline.text.replace('\n', " ");
+        }
+        inlines
+    }
+
+    fn resolve(&self, href: &str) -> String {
+        self.base
+            .and_then(|base| base.join(href).ok())
+            .map(|url| url.to_string())
+            .unwrap_or_else(|| href.to_string())
+    }
+}
+
+fn code_text(pre: ElementRef) -> String {
+    let text: String = pre.text().collect();
+    text.trim_end().trim_start_matches('\n').replace('\t', "    ")
+}
+
+// Highlighters and sites mark the language as `language-rust` or `lang-rust`
+// on the <pre> or on the <code> inside it.
+fn code_language(pre: ElementRef) -> Option<String> {
+    let code = pre.child_elements().find(|child| child.value().name() == "code");
+    [Some(pre), code]
+        .into_iter()
+        .flatten()
+        .flat_map(|element| element.value().classes())
+        .find_map(|class| {
+            class
+                .strip_prefix("language-")
+                .or_else(|| class.strip_prefix("lang-"))
+                .map(str::to_string)
+        })
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !in_space {
+                out.push(' ');
+            }
+            in_space = true;
+        } else {
+            out.push(c);
+            in_space = false;
+        }
+    }
+    out
+}
+
+fn push_text(inlines: &mut Vec<Inline>, text: &str, style: &Inline) {
+    let mut text = collapse_whitespace(text);
+    let after_space = inlines
+        .last()
+        .map(|last| last.text.ends_with([' ', '\n']))
+        .unwrap_or(true);
+    if after_space {
+        text = text.trim_start().to_string();
+    }
+    if text.is_empty() {
+        return;
+    }
+    match inlines.last_mut() {
+        Some(last) if last.same_style(style) => last.text.push_str(&text),
+        _ => inlines.push(Inline { text, ..style.clone() }),
+    }
+}
+
+fn push_break(inlines: &mut Vec<Inline>, style: &Inline) {
+    if let Some(last) = inlines.last_mut() {
+        let trimmed = last.text.trim_end_matches(' ').len();
+        last.text.truncate(trimmed);
+    }
+    if inlines.is_empty() {
+        return;
+    }
+    match inlines.last_mut() {
+        Some(last) if last.same_style(style) => last.text.push('\n'),
+        _ => inlines.push(Inline { text: "\n".to_string(), ..style.clone() }),
+    }
+}
+
+fn trim(inlines: &mut Vec<Inline>) {
+    if let Some(first) = inlines.first_mut() {
+        first.text = first.text.trim_start().to_string();
+    }
+    if let Some(last) = inlines.last_mut() {
+        last.text = last.text.trim_end().to_string();
+    }
+    inlines.retain(|inline| !inline.text.is_empty());
+}
+
+fn flush(inlines: &mut Vec<Inline>, blocks: &mut Vec<Block>) {
+    trim(inlines);
+    if !inlines.is_empty() {
+        blocks.push(Block::Paragraph(std::mem::take(inlines)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> Url {
+        Url::parse("https://example.com/posts/one.html").unwrap()
+    }
+
+    #[test]
+    fn blocks_keep_structure_and_drop_chrome() {
+        let html = r#"
+            <nav><a href="/">Home</a></nav>
+            <h2>  Title <em>here</em> </h2>
+            <p>First   line<br>second <strong>bold</strong></p>
+            <script>alert(1)</script>
+            <ol start="3"><li>three</li><li>four <ul><li>nested</li></ul></li></ol>
+            <blockquote><p>quoted</p></blockquote>
+            <hr>"#;
+        let blocks = parse(html, None);
+        assert_eq!(
+            to_text(&blocks),
+            "Title here\nFirst line\nsecond bold\n• three\n• four • nested\nquoted"
+        );
+        assert!(matches!(blocks[0], Block::Heading { level: 2, .. }));
+        let Block::Paragraph(paragraph) = &blocks[1] else {
+            panic!("expected a paragraph");
+        };
+        assert!(paragraph.last().is_some_and(|inline| inline.bold && inline.text == "bold"));
+        assert!(matches!(blocks[2], Block::List { ordered: true, start: 3, .. }));
+        assert!(matches!(blocks.last(), Some(Block::Rule)));
+    }
+
+    #[test]
+    fn links_and_images_resolve_against_the_base() {
+        let html = r##"
+            <p><a href="two.html">next</a>, <a href="#top">top</a>,
+               <a href="javascript:void(0)">js</a> and <a href="two.html">again</a></p>
+            <img data-src="/img/a.png" alt=" A ">
+            <ul><li><a href="https://other.example/">elsewhere</a></li></ul>"##;
+        let blocks = parse(html, Some(&base()));
+        assert_eq!(
+            links(&blocks),
+            ["https://example.com/posts/two.html", "https://other.example/"]
+        );
+        assert_eq!(images(&blocks), ["https://example.com/img/a.png"]);
+        assert!(matches!(&blocks[1], Block::Image { alt, .. } if alt == "A"));
+    }
+
+    #[test]
+    fn table_cells_keep_their_links() {
+        let html = r#"<table>
+            <tr><th>Name</th><th>Site</th></tr>
+            <tr><td><p>alpha</p><p>beta</p></td><td>see <a href="/alpha">alpha</a> site</td></tr>
+            </table><p><a href="/after">after</a></p>"#;
+        let blocks = parse(html, Some(&base()));
+        let Block::Table { header, rows } = &blocks[0] else {
+            panic!("expected a table");
+        };
+        assert!(header);
+        let texts: Vec<Vec<String>> = rows
+            .iter()
+            .map(|row| row.iter().map(|cell| inline_text(cell)).collect())
+            .collect();
+        assert_eq!(texts, [["Name", "Site"], ["alpha beta", "see alpha site"]]);
+        assert_eq!(rows[1][1][1].link.as_deref(), Some("https://example.com/alpha"));
+        assert_eq!(
+            links(&blocks),
+            ["https://example.com/alpha", "https://example.com/after"]
+        );
+    }
+
+    #[test]
+    fn tables_cached_with_plain_text_cells_still_load() {
+        let cached = "- !Table\n  header: false\n  rows:\n  - - one\n    - two\n";
+        let blocks: Vec<Block> = serde_yaml::from_str(cached).unwrap();
+        assert_eq!(to_text(&blocks), "one | two");
+
+        let html = r#"<table><tr><td><a href="https://x.example/">x</a></td></tr></table>"#;
+        let saved = serde_yaml::to_string(&parse(html, None)).unwrap();
+        let reloaded: Vec<Block> = serde_yaml::from_str(&saved).unwrap();
+        assert_eq!(links(&reloaded), ["https://x.example/"]);
+    }
+
+    #[test]
+    fn code_blocks_keep_text_and_language() {
+        let html = "<pre class=\"lang-py\">\n\tprint(1)\n</pre><pre><code class=\"language-rust\">fn main() {}</code></pre>";
+        let blocks = parse(html, None);
+        let languages: Vec<Option<&str>> = blocks
+            .iter()
+            .map(|block| match block {
+                Block::Code { language, .. } => language.as_deref(),
+                _ => panic!("expected code"),
+            })
+            .collect();
+        assert_eq!(languages, [Some("py"), Some("rust")]);
+        assert_eq!(to_text(&blocks), "    print(1)\nfn main() {}");
+        assert_eq!(code_width(&blocks), 12);
+    }
+}
@@ -1,7 +1,10 @@
+use crate::config::Retention;
 use anyhow::{Context, Result};
 use atom_syndication::Feed as AtomFeed;
 use chrono::{DateTime, Utc};
 use reqwest::blocking::Client;
+use reqwest::header::{CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -10,6 +13,44 @@ pub struct FeedSource {
     pub url: String,
     #[serde(default)]
     pub tags: Vec<String>,
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub max_items: Option<usize>,
+    #[serde(default)]
+    pub max_age_days: Option<u32>,
+}
+
+impl FeedSource {
+    /// Stable key for on-disk state: the explicit `id` from the config, or a
+    /// hash of the URL (see `url_key`) so renaming a feed keeps its cache.
+    pub fn id(&self) -> String {
+        match &self.id {
+            Some(id) => id.clone(),
+            None => crate::cache::stable_hash(&url_key(&self.url)),
+        }
+    }
+
+    /// This feed's own limits, falling back to the global ones.
+    pub fn retention(&self, global: Retention) -> Retention {
+        Retention {
+            max_items: self.max_items.or(global.max_items),
+            max_age_days: self.max_age_days.or(global.max_age_days),
+        }
+    }
+}
+
+/// Canonical form of a feed URL for comparing feeds and deriving ids. Only
+/// the scheme and host are case-insensitive, and nothing is fetched, so the
+/// key of a feed never changes.
+pub fn url_key(url: &str) -> String {
+    let url = url.trim().trim_end_matches('/');
+    let (scheme, rest) = match url.split_once("://") {
+        Some((scheme, rest)) => (format!("{}://", scheme.to_lowercase()), rest),
+        None => (String::new(), url),
+    };
+    let host_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    format!("{}{}{}", scheme, rest[..host_end].to_lowercase(), &rest[host_end..])
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -20,10 +61,55 @@ pub struct FeedItem {
     pub source_name: String,
     #[serde(default)]
     pub is_youtube_short: bool,
+    #[serde(default)]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub content: Option<String>,
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub categories: Vec<String>,
+    #[serde(default)]
+    pub guid: Option<String>,
     #[serde(skip)]
     pub seen: bool,
     #[serde(skip)]
     pub liked: bool,
+    /// Matched text from a search, with hits wrapped in `SNIPPET_START`/`SNIPPET_END`
+    #[serde(skip)]
+    pub snippet: Option<String>,
+}
+
+pub const SNIPPET_START: char = '\u{1}';
+pub const SNIPPET_END: char = '\u{2}';
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Validators {
+    #[serde(default)]
+    pub etag: Option<String>,
+    #[serde(default)]
+    pub last_modified: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct HttpStatusError(pub u16);
+
+impl std::fmt::Display for HttpStatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match StatusCode::from_u16(self.0).ok().and_then(|s| s.canonical_reason()) {
+            Some(reason) => write!(f, "HTTP {} {}", self.0, reason),
+            None => write!(f, "HTTP {}", self.0),
+        }
+    }
+}
+
+impl std::error::Error for HttpStatusError {}
+
+pub enum FetchResult {
+    /// The items, the validators for the next request and the response's
+    /// status, any 2xx other than 304.
+    Modified(Vec<FeedItem>, Validators, StatusCode),
+    NotModified,
 }
 
 fn create_client() -> Result<Client> {
@@ -34,6 +120,11 @@ fn create_client() -> Result<Client> {
 }
 
 fn normalize_url(url: &str) -> String {
+    // Addresses typed without a scheme, like `example.com`, are taken as https
+    if !url.contains("://") {
+        return normalize_url(&format!("https://{}", url));
+    }
+
     if let Some(id) = url
         .strip_prefix("https://rss.app/feed/")
         .or_else(|| url.strip_prefix("http://rss.app/feed/"))
@@ -91,20 +182,214 @@ fn fetch_channel_id_from_handle(handle: &str) -> Result<String> {
     anyhow::bail!("Could not find channel ID for handle: {}", handle)
 }
 
+#[derive(Clone)]
+pub struct DiscoveredFeed {
+    pub url: String,
+    pub title: Option<String>,
+}
+
+const COMMON_FEED_PATHS: &[&str] = &[
+    "/feed",
+    "/rss",
+    "/rss.xml",
+    "/atom.xml",
+    "/feed.xml",
+    "/index.xml",
+    "/feed.json",
+];
+
+/// What the address given when adding a feed turned out to be.
+pub enum Resolved {
+    Feed(Vec<FeedItem>),
+    /// A web page, with the feeds found for it.
+    Page(Vec<DiscoveredFeed>),
+}
+
+/// Fetches `url` once: a feed is parsed, and any other page is searched for
+/// feeds.
+pub fn resolve(url: &str) -> Result<Resolved> {
+    let response = create_client()?.get(normalize_url(url)).send()?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(HttpStatusError(status.as_u16()).into());
+    }
+    // Links on the page are relative to where any redirects ended up
+    let base = response.url().clone();
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(String::from);
+    let content = response.bytes()?;
+
+    if let Ok(items) = parse_feed(content_type.as_deref(), &content) {
+        return Ok(Resolved::Feed(items));
+    }
+    Ok(Resolved::Page(discover_feeds(&base, &String::from_utf8_lossy(&content))))
+}
+
+// The feeds a page advertises in `<link rel="alternate">` tags, followed by
+// those answering at the usual paths. Sites can serve several formats there,
+// so every path is tried, all at once.
+fn discover_feeds(base: &url::Url, html: &str) -> Vec<DiscoveredFeed> {
+    let document = scraper::Html::parse_document(html);
+    let selector = scraper::Selector::parse("link[rel~=alternate][href]").unwrap();
+
+    let mut feeds: Vec<DiscoveredFeed> = Vec::new();
+    for link in document.select(&selector) {
+        let link_type = link.value().attr("type").unwrap_or("").to_lowercase();
+        let is_feed = matches!(
+            link_type.as_str(),
+            "application/rss+xml" | "application/atom+xml" | "application/feed+json"
+        );
+        if !is_feed {
+            continue;
+        }
+        let Some(url) = link.value().attr("href").and_then(|h| base.join(h).ok()) else {
+            continue;
+        };
+        if feeds.iter().all(|f| url_key(&f.url) != url_key(url.as_str())) {
+            feeds.push(DiscoveredFeed {
+                url: url.to_string(),
+                title: link.value().attr("title").map(|t| t.trim().to_string()),
+            });
+        }
+    }
+
+    let candidates: Vec<String> = COMMON_FEED_PATHS
+        .iter()
+        .filter_map(|path| base.join(path).ok())
+        .map(String::from)
+        .filter(|url| feeds.iter().all(|f| url_key(&f.url) != url_key(url)))
+        .collect();
+    let probed: Vec<Option<DiscoveredFeed>> = std::thread::scope(|scope| {
+        let probes: Vec<_> = candidates
+            .iter()
+            .map(|url| {
+                scope.spawn(move || {
+                    let items = fetch_feed(url).ok()?;
+                    Some(DiscoveredFeed {
+                        url: url.clone(),
+                        title: items.first().map(|i| i.source_name.clone()),
+                    })
+                })
+            })
+            .collect();
+        probes.into_iter().map(|probe| probe.join().ok().flatten()).collect()
+    });
+    feeds.extend(probed.into_iter().flatten());
+    feeds
+}
+
 pub fn fetch_feed(url: &str) -> Result<Vec<FeedItem>> {
+    match fetch_feed_conditional(url, &Validators::default())? {
+        FetchResult::Modified(items, _, _) => Ok(items),
+        FetchResult::NotModified => anyhow::bail!("Server returned 304 for an unconditional request"),
+    }
+}
+
+pub fn fetch_feed_conditional(url: &str, validators: &Validators) -> Result<FetchResult> {
     let client = create_client()?;
     let normalized_url = normalize_url(url);
-    let content = client.get(&normalized_url).send()?.bytes()?;
 
-    if let Ok(items) = parse_rss(&content) {
-        return Ok(items);
+    let mut request = client.get(&normalized_url);
+    if let Some(etag) = &validators.etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = &validators.last_modified {
+        request = request.header(IF_MODIFIED_SINCE, last_modified);
+    }
+    let response = request.send()?;
+
+    let status = response.status();
+    if status == StatusCode::NOT_MODIFIED {
+        return Ok(FetchResult::NotModified);
+    }
+    if !status.is_success() {
+        return Err(HttpStatusError(status.as_u16()).into());
+    }
+
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(String::from)
+    };
+    let new_validators = Validators {
+        etag: header(ETAG),
+        last_modified: header(LAST_MODIFIED),
+    };
+    let content_type = header(CONTENT_TYPE);
+
+    let content = response.bytes()?;
+    let items = parse_feed(content_type.as_deref(), &content)?;
+
+    Ok(FetchResult::Modified(items, new_validators, status))
+}
+
+fn parse_feed(content_type: Option<&str>, content: &[u8]) -> Result<Vec<FeedItem>> {
+    match detect_format(content_type, content) {
+        Some(FeedFormat::Rss) => parse_rss(content).context("Failed to parse RSS feed"),
+        Some(FeedFormat::Atom) => parse_atom(content).context("Failed to parse Atom feed"),
+        Some(FeedFormat::Json) => parse_json_feed(content).context("Failed to parse JSON Feed"),
+        None => anyhow::bail!("Response is not an RSS, Atom or JSON feed"),
     }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum FeedFormat {
+    Rss,
+    Atom,
+    Json,
+}
+
+pub fn detect_format(content_type: Option<&str>, content: &[u8]) -> Option<FeedFormat> {
+    let mime = content_type
+        .and_then(|ct| ct.split(';').next())
+        .map(|ct| ct.trim().to_lowercase())
+        .unwrap_or_default();
+
+    let declared = match mime.as_str() {
+        "application/feed+json" | "application/json" => Some(FeedFormat::Json),
+        "application/atom+xml" => Some(FeedFormat::Atom),
+        "application/rss+xml" | "application/rdf+xml" => Some(FeedFormat::Rss),
+        _ => None,
+    };
+
+    // Servers often send generic (text/xml, application/json) or wrong
+    // types, so the document's root wins whenever it is recognisable
+    sniff_format(content).or(declared)
+}
 
-    if let Ok(items) = parse_atom(&content) {
-        return Ok(items);
+fn sniff_format(content: &[u8]) -> Option<FeedFormat> {
+    let text = String::from_utf8_lossy(&content[..content.len().min(4096)]);
+    let mut rest = text.trim_start_matches('\u{feff}').trim_start();
+
+    // JSON Feed is the only JSON format, and its `version` may come late in
+    // the document or be missing, so any object is parsed as one
+    if rest.starts_with('{') {
+        return Some(FeedFormat::Json);
     }
 
-    anyhow::bail!("Failed to parse feed as RSS or Atom")
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        if rest.starts_with('?') || rest.starts_with('!') {
+            continue;
+        }
+        let name: String = rest
+            .chars()
+            .take_while(|c| !c.is_whitespace() && *c != '>' && *c != '/')
+            .collect();
+        let local_name = name.rsplit(':').next().unwrap_or(&name);
+        return match local_name {
+            "rss" | "RDF" => Some(FeedFormat::Rss),
+            "feed" => Some(FeedFormat::Atom),
+            _ => None,
+        };
+    }
+
+    None
 }
 
 fn parse_rss(content: &[u8]) -> Result<Vec<FeedItem>> {
@@ -127,14 +412,28 @@ fn parse_rss(content: &[u8]) -> Result<Vec<FeedItem>> {
 
             let is_youtube_short = is_youtube_short(&link, &title, description);
 
+            let author = item
+                .author()
+                .map(String::from)
+                .or_else(|| {
+                    item.dublin_core_ext()
+                        .and_then(|dc| dc.creators().first().cloned())
+                });
+
             FeedItem {
                 title,
                 link,
                 date,
                 source_name: source_name.clone(),
                 is_youtube_short,
+                summary: non_empty(item.description()).map(crate::reader::html_to_text),
+                content: non_empty(item.content()).map(String::from),
+                author,
+                categories: item.categories().iter().map(|c| c.name().to_string()).collect(),
+                guid: item.guid().map(|g| g.value().to_string()),
                 seen: false,
                 liked: false,
+                snippet: None,
             }
         })
         .collect();
@@ -169,8 +468,124 @@ fn parse_atom(content: &[u8]) -> Result<Vec<FeedItem>> {
                 date,
                 source_name: source_name.clone(),
                 is_youtube_short,
+                summary: non_empty(Some(summary)).map(crate::reader::html_to_text),
+                content: non_empty(entry.content().and_then(|c| c.value())).map(String::from),
+                author: entry.authors().first().map(|a| a.name().to_string()),
+                categories: entry.categories().iter().map(|c| c.term().to_string()).collect(),
+                guid: non_empty(Some(entry.id())).map(String::from),
+                seen: false,
+                liked: false,
+                snippet: None,
+            }
+        })
+        .collect();
+
+    Ok(items)
+}
+
+#[derive(Deserialize)]
+struct JsonFeed {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    items: Vec<JsonFeedItem>,
+}
+
+#[derive(Deserialize)]
+struct JsonFeedItem {
+    #[serde(default)]
+    id: Option<serde_json::Value>,
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    external_url: Option<String>,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    content_html: Option<String>,
+    #[serde(default)]
+    content_text: Option<String>,
+    #[serde(default)]
+    summary: Option<String>,
+    #[serde(default)]
+    date_published: Option<String>,
+    #[serde(default)]
+    date_modified: Option<String>,
+    // `author` is JSON Feed 1.0, `authors` replaced it in 1.1
+    #[serde(default)]
+    author: Option<JsonFeedAuthor>,
+    #[serde(default)]
+    authors: Vec<JsonFeedAuthor>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct JsonFeedAuthor {
+    #[serde(default)]
+    name: Option<String>,
+}
+
+fn parse_json_feed(content: &[u8]) -> Result<Vec<FeedItem>> {
+    let feed: JsonFeed = serde_json::from_slice(content)?;
+    let source_name = feed.title;
+
+    let items: Vec<FeedItem> = feed
+        .items
+        .into_iter()
+        .map(|item| {
+            let date = item
+                .date_published
+                .as_deref()
+                .or(item.date_modified.as_deref())
+                .and_then(|d| DateTime::parse_from_rfc3339(d).ok())
+                .map(|d| d.with_timezone(&Utc))
+                .unwrap_or_else(Utc::now);
+
+            let link = item.url.or(item.external_url);
+            let summary = non_empty(item.summary.as_deref()).map(String::from);
+            let content = non_empty(item.content_html.as_deref())
+                .or(non_empty(item.content_text.as_deref()))
+                .map(String::from);
+
+            let title = non_empty(item.title.as_deref())
+                .map(String::from)
+                .or_else(|| {
+                    summary
+                        .as_deref()
+                        .or(item.content_text.as_deref())
+                        .map(|text| text.chars().take(80).collect())
+                })
+                .unwrap_or_else(|| "Untitled".to_string());
+
+            let is_youtube_short =
+                is_youtube_short(&link, &title, summary.as_deref().unwrap_or(""));
+
+            let author = item
+                .authors
+                .into_iter()
+                .chain(item.author)
+                .find_map(|a| a.name);
+
+            let guid = item.id.map(|id| match id {
+                serde_json::Value::String(s) => s,
+                other => other.to_string(),
+            });
+
+            FeedItem {
+                title,
+                link,
+                date,
+                source_name: source_name.clone(),
+                is_youtube_short,
+                summary,
+                content,
+                author,
+                categories: item.tags,
+                guid,
                 seen: false,
                 liked: false,
+                snippet: None,
             }
         })
         .collect();
@@ -178,6 +593,10 @@ fn parse_atom(content: &[u8]) -> Result<Vec<FeedItem>> {
     Ok(items)
 }
 
+fn non_empty(value: Option<&str>) -> Option<&str> {
+    value.filter(|v| !v.trim().is_empty())
+}
+
 fn is_youtube_short(link: &Option<String>, title: &str, description: &str) -> bool {
     if let Some(url) = link {
         if !url.contains("youtube.com") && !url.contains("youtu.be") {
@@ -214,3 +633,227 @@ fn is_youtube_short(link: &Option<String>, title: &str, description: &str) -> bo
 
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{rss, serve, Request, Response};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    const ETAG_V1: &str = "\"v1\"";
+    const LAST_MODIFIED_V1: &str = "Mon, 01 Jan 2024 00:00:00 GMT";
+
+    fn conditional_feed(request: &Request) -> Response {
+        let header = |name: &str| request.headers.get(name).map(String::as_str);
+        if header("if-none-match") == Some(ETAG_V1)
+            || header("if-modified-since") == Some(LAST_MODIFIED_V1)
+        {
+            return Response::status(304);
+        }
+        Response::ok("application/rss+xml", &rss("Feed", &["one", "two"]))
+            .header("ETag", ETAG_V1)
+            .header("Last-Modified", LAST_MODIFIED_V1)
+    }
+
+    #[test]
+    fn first_fetch_keeps_validators_and_the_status() {
+        let url = format!("{}/feed.xml", serve(conditional_feed));
+        match fetch_feed_conditional(&url, &Validators::default()).unwrap() {
+            FetchResult::Modified(items, validators, status) => {
+                assert_eq!(items.len(), 2);
+                assert_eq!(validators.etag.as_deref(), Some(ETAG_V1));
+                assert_eq!(validators.last_modified.as_deref(), Some(LAST_MODIFIED_V1));
+                assert_eq!(status, StatusCode::OK);
+            }
+            FetchResult::NotModified => panic!("first fetch should download the feed"),
+        }
+    }
+
+    #[test]
+    fn matching_etag_or_last_modified_is_not_modified() {
+        let url = format!("{}/feed.xml", serve(conditional_feed));
+        let etag_only = Validators {
+            etag: Some(ETAG_V1.to_string()),
+            last_modified: None,
+        };
+        let last_modified_only = Validators {
+            etag: None,
+            last_modified: Some(LAST_MODIFIED_V1.to_string()),
+        };
+        for validators in [etag_only, last_modified_only] {
+            assert!(matches!(
+                fetch_feed_conditional(&url, &validators).unwrap(),
+                FetchResult::NotModified
+            ));
+        }
+    }
+
+    #[test]
+    fn stale_validators_download_the_feed_again() {
+        let url = format!("{}/feed.xml", serve(conditional_feed));
+        let stale = Validators {
+            etag: Some("\"v0\"".to_string()),
+            last_modified: None,
+        };
+        assert!(matches!(
+            fetch_feed_conditional(&url, &stale).unwrap(),
+            FetchResult::Modified(..)
+        ));
+    }
+
+    const BLOG_PAGE: &str = r#"<html><head>
+        <link rel="stylesheet" href="/style.css">
+        <link rel="alternate" type="application/rss+xml" title=" Posts " href="posts.rss">
+        <link rel="alternate" type="application/atom+xml" href="/atom.xml">
+        <link rel="alternate" type="application/atom+xml" href="/atom.xml/">
+        </head><body>Hello</body></html>"#;
+
+    const JSON_FEED: &str = r#"{"version": "https://jsonfeed.org/version/1.1", "title": "Json",
+        "items": [{"id": "1", "url": "http://example.com/1", "title": "One"}]}"#;
+
+    fn blog(page_hits: Arc<AtomicUsize>) -> impl Fn(&Request) -> Response + Send + Sync {
+        move |request| match request.path.as_str() {
+            "/blog/" => {
+                page_hits.fetch_add(1, Ordering::SeqCst);
+                Response::ok("text/html", BLOG_PAGE)
+            }
+            "/blog/posts.rss" => Response::ok("application/rss+xml", &rss("Posts", &["a"])),
+            "/atom.xml" => Response::ok("application/rss+xml", &rss("Atom", &["b"])),
+            "/feed.json" => Response::ok("application/json", JSON_FEED),
+            "/feed" => Response::ok("text/html", "<html><body>Not a feed</body></html>"),
+            _ => Response::status(404),
+        }
+    }
+
+    #[test]
+    fn page_lists_link_tags_then_common_paths_without_duplicates() {
+        let page_hits = Arc::new(AtomicUsize::new(0));
+        let base = serve(blog(Arc::clone(&page_hits)));
+
+        let Resolved::Page(feeds) = resolve(&format!("{}/blog/", base)).unwrap() else {
+            panic!("an HTML page should not resolve to a feed");
+        };
+        let found: Vec<(&str, Option<&str>)> = feeds
+            .iter()
+            .map(|f| (f.url.strip_prefix(&base).unwrap(), f.title.as_deref()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("/blog/posts.rss", Some("Posts")),
+                ("/atom.xml", None),
+                ("/feed.json", Some("Json")),
+            ]
+        );
+        assert_eq!(page_hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn feed_address_resolves_to_its_items() {
+        let base = serve(blog(Arc::new(AtomicUsize::new(0))));
+        match resolve(&format!("{}/feed.json", base)).unwrap() {
+            Resolved::Feed(items) => assert_eq!(items.len(), 1),
+            Resolved::Page(_) => panic!("a feed should resolve to its items"),
+        }
+    }
+
+    #[test]
+    fn missing_page_is_an_error() {
+        let base = serve(blog(Arc::new(AtomicUsize::new(0))));
+        assert!(resolve(&format!("{}/nowhere", base)).is_err());
+    }
+
+    #[test]
+    fn document_root_decides_the_format() {
+        let cases: &[(&str, FeedFormat)] = &[
+            ("<?xml version=\"1.0\"?>\n<!-- hi --><rss version=\"2.0\">", FeedFormat::Rss),
+            ("<rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">", FeedFormat::Rss),
+            ("<feed xmlns=\"http://www.w3.org/2005/Atom\">", FeedFormat::Atom),
+            ("<atom:feed xmlns:atom=\"http://www.w3.org/2005/Atom\">", FeedFormat::Atom),
+            ("\u{feff}  <rss>", FeedFormat::Rss),
+            ("\u{feff}\n{\"title\": \"No version\"}", FeedFormat::Json),
+        ];
+        for (content, format) in cases {
+            // A wrong or generic content type doesn't override the root
+            for content_type in [None, Some("text/xml"), Some("application/atom+xml")] {
+                assert!(
+                    detect_format(content_type, content.as_bytes()) == Some(*format),
+                    "{content:?} as {content_type:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn content_type_decides_when_the_root_is_unknown() {
+        let detect = |content_type| detect_format(Some(content_type), b"<html>");
+        assert!(detect("application/feed+json; charset=utf-8") == Some(FeedFormat::Json));
+        assert!(detect("application/json") == Some(FeedFormat::Json));
+        assert!(detect("Application/Atom+XML") == Some(FeedFormat::Atom));
+        assert!(detect("application/rdf+xml") == Some(FeedFormat::Rss));
+        assert!(detect("text/html").is_none());
+        assert!(detect_format(None, b"").is_none());
+    }
+
+    #[test]
+    fn json_feed_1_0_uses_author() {
+        let content = r#"{
+            "version": "https://jsonfeed.org/version/1",
+            "title": "Old",
+            "author": {"name": "Feed author"},
+            "items": [{
+                "id": 42,
+                "url": "https://example.com/1",
+                "title": "One",
+                "content_text": "Body",
+                "date_published": "2024-01-02T03:04:05+01:00",
+                "author": {"name": "Ann"}
+            }]
+        }"#;
+        let items = parse_feed(Some("application/json"), content.as_bytes()).unwrap();
+        let item = &items[0];
+        assert_eq!(item.source_name, "Old");
+        assert_eq!(item.author.as_deref(), Some("Ann"));
+        assert_eq!(item.guid.as_deref(), Some("42"));
+        assert_eq!(item.content.as_deref(), Some("Body"));
+        assert_eq!(item.date.to_rfc3339(), "2024-01-02T02:04:05+00:00");
+    }
+
+    #[test]
+    fn json_feed_1_1_uses_authors_and_falls_back_for_titles() {
+        let content = r#"{
+            "version": "https://jsonfeed.org/version/1.1",
+            "title": "New",
+            "items": [{
+                "id": "a",
+                "external_url": "https://elsewhere.example/a",
+                "summary": "A title-less note",
+                "content_html": "<p>Note</p>",
+                "date_modified": "2024-05-06T07:08:09Z",
+                "authors": [{"url": "https://example.com"}, {"name": "Bo"}],
+                "tags": ["notes"]
+            }]
+        }"#;
+        let items = parse_feed(Some("application/feed+json"), content.as_bytes()).unwrap();
+        let item = &items[0];
+        assert_eq!(item.title, "A title-less note");
+        assert_eq!(item.link.as_deref(), Some("https://elsewhere.example/a"));
+        assert_eq!(item.author.as_deref(), Some("Bo"));
+        assert_eq!(item.content.as_deref(), Some("<p>Note</p>"));
+        assert_eq!(item.categories, ["notes"]);
+        assert_eq!(item.date.to_rfc3339(), "2024-05-06T07:08:09+00:00");
+    }
+
+    #[test]
+    fn json_feed_without_a_version_served_as_json_parses() {
+        let content = format!(
+            r#"{{"title": "Bare", "description": "{}", "items": [{{"title": "Late"}}]}}"#,
+            "x".repeat(5000)
+        );
+        let items = parse_feed(Some("application/json"), content.as_bytes()).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].title, "Late");
+        assert!(parse_feed(Some("application/json"), b"[1, 2]").is_err());
+    }
+}
@@ -0,0 +1,226 @@
+use crate::config::ImageProtocol;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use crossterm::{cursor::MoveTo, queue};
+use image::imageops::{self, FilterType};
+use image::{ImageFormat, RgbImage};
+use ratatui::layout::Rect;
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Write as _;
+use std::io::{self, Cursor, Write};
+
+/// Picks the protocol the terminal understands when set to `Auto`. Sixel
+/// support can't be told from the environment everywhere, so terminals not
+/// recognized here get half blocks unless configured otherwise.
+pub fn resolve(setting: ImageProtocol) -> ImageProtocol {
+    if setting != ImageProtocol::Auto {
+        return setting;
+    }
+    let var = |name| env::var(name).unwrap_or_default();
+    let term = var("TERM");
+    let program = var("TERM_PROGRAM");
+
+    // Multiplexers don't pass graphics through by default
+    if env::var_os("TMUX").is_some() || term.starts_with("screen") {
+        ImageProtocol::Halfblocks
+    } else if term.contains("kitty") || term.contains("ghostty") || env::var_os("KITTY_WINDOW_ID").is_some() {
+        ImageProtocol::Kitty
+    } else if program == "iTerm.app" || program == "WezTerm" || var("LC_TERMINAL") == "iTerm2" {
+        ImageProtocol::Iterm2
+    } else if term.starts_with("foot") || term.starts_with("mlterm") || program == "mintty" {
+        ImageProtocol::Sixel
+    } else {
+        ImageProtocol::Halfblocks
+    }
+}
+
+/// The size of a terminal cell in pixels. Terminals that don't report it are
+/// taken to have 8×16 cells.
+pub fn cell_size() -> (u16, u16) {
+    match crossterm::terminal::window_size() {
+        Ok(size) if size.width > 0 && size.height > 0 && size.columns > 0 && size.rows > 0 => (
+            (size.width / size.columns).max(1),
+            (size.height / size.rows).max(1),
+        ),
+        _ => (8, 16),
+    }
+}
+
+/// Where an image goes on screen. When the image is scrolled partly out of
+/// view, `area` holds the visible rows, starting `crop_top` rows into its
+/// `rows`.
+#[derive(Clone, PartialEq)]
+pub struct Placement {
+    pub src: String,
+    pub area: Rect,
+    pub crop_top: u16,
+    pub rows: u16,
+}
+
+/// Draws images into the cells the reader leaves for them. The terminal
+/// keeps an image until something is drawn over it, so images are only sent
+/// again when a placement changes.
+#[derive(Default)]
+pub struct Graphics {
+    shown: Vec<Placement>,
+    screen: Rect,
+}
+
+impl Graphics {
+    pub fn show(
+        &mut self,
+        protocol: ImageProtocol,
+        placements: Vec<Placement>,
+        screen: Rect,
+        images: &HashMap<String, Option<RgbImage>>,
+    ) -> io::Result<()> {
+        // A resize clears the screen, so everything has to be drawn again
+        if placements == self.shown && screen == self.screen {
+            return Ok(());
+        }
+
+        let mut out = io::stdout().lock();
+        if protocol == ImageProtocol::Kitty && !self.shown.is_empty() {
+            // Kitty draws images on their own layer, so old ones must be removed
+            out.write_all(b"\x1b_Ga=d,d=A,q=2\x1b\\")?;
+        }
+        let cell = cell_size();
+        for placement in &placements {
+            let Some(Some(image)) = images.get(&placement.src) else {
+                continue;
+            };
+            let image = visible_part(image, placement, cell);
+            let sequence = match protocol {
+                ImageProtocol::Kitty => kitty(&image, placement.area),
+                ImageProtocol::Iterm2 => iterm2(&image, placement.area),
+                ImageProtocol::Sixel => sixel(&image),
+                _ => continue,
+            };
+            queue!(out, MoveTo(placement.area.x, placement.area.y))?;
+            out.write_all(sequence.as_bytes())?;
+        }
+        out.flush()?;
+
+        self.shown = placements;
+        self.screen = screen;
+        Ok(())
+    }
+}
+
+// The rows of the image that are on screen, scaled to the pixels they cover.
+fn visible_part(image: &RgbImage, placement: &Placement, cell: (u16, u16)) -> RgbImage {
+    let (width, height) = image.dimensions();
+    let rows = u32::from(placement.rows.max(1));
+    let top = height * u32::from(placement.crop_top) / rows;
+    let bottom = (height * u32::from(placement.crop_top + placement.area.height) / rows).min(height);
+    let part = imageops::crop_imm(image, 0, top, width, bottom.saturating_sub(top).max(1)).to_image();
+    imageops::resize(
+        &part,
+        u32::from(placement.area.width) * u32::from(cell.0),
+        u32::from(placement.area.height) * u32::from(cell.1),
+        FilterType::Triangle,
+    )
+}
+
+fn png(image: &RgbImage) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let _ = image.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png);
+    bytes
+}
+
+// https://sw.kovidgoyal.net/kitty/graphics-protocol/ — the data is sent in
+// chunks of at most 4096 bytes. `q=2` keeps the terminal from answering on
+// stdin, and `C=1` from moving the cursor.
+fn kitty(image: &RgbImage, area: Rect) -> String {
+    let data = STANDARD.encode(png(image));
+    let chunks: Vec<&str> = (0..data.len())
+        .step_by(4096)
+        .map(|start| &data[start..(start + 4096).min(data.len())])
+        .collect();
+    let mut out = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        if i == 0 {
+            let _ = write!(
+                out,
+                "\x1b_Ga=T,f=100,q=2,C=1,c={},r={},m={};{}\x1b\\",
+                area.width, area.height, more, chunk
+            );
+        } else {
+            let _ = write!(out, "\x1b_Gm={};{}\x1b\\", more, chunk);
+        }
+    }
+    out
+}
+
+// https://iterm2.com/documentation-images.html — width and height are in cells.
+fn iterm2(image: &RgbImage, area: Rect) -> String {
+    let data = png(image);
+    format!(
+        "\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=0:{}\x07",
+        data.len(),
+        area.width,
+        area.height,
+        STANDARD.encode(&data)
+    )
+}
+
+// Sixel paints bands six pixels high, one color at a time. Colors come from a
+// 6×6×6 cube, which is plenty for the size images are drawn at.
+fn sixel(image: &RgbImage) -> String {
+    let (width, height) = image.dimensions();
+    let (width, height) = (width as usize, height as usize);
+    let mut out = format!("\x1bPq\"1;1;{};{}", width, height);
+    for i in 0..216 {
+        let _ = write!(out, "#{};2;{};{};{}", i, i / 36 * 20, i / 6 % 6 * 20, i % 6 * 20);
+    }
+
+    let level = |value: u8| (usize::from(value) * 5 + 127) / 255;
+    let mut band = vec![0; width * 6];
+    for top in (0..height).step_by(6) {
+        let rows = (height - top).min(6);
+        for dy in 0..rows {
+            for x in 0..width {
+                let pixel = image.get_pixel(x as u32, (top + dy) as u32);
+                band[dy * width + x] = level(pixel[0]) * 36 + level(pixel[1]) * 6 + level(pixel[2]);
+            }
+        }
+        let mut colors = band[..rows * width].to_vec();
+        colors.sort_unstable();
+        colors.dedup();
+
+        for (n, &color) in colors.iter().enumerate() {
+            if n > 0 {
+                out.push('$');
+            }
+            let _ = write!(out, "#{}", color);
+            let mut run = (' ', 0);
+            for x in 0..width {
+                let bits = (0..rows)
+                    .filter(|dy| band[dy * width + x] == color)
+                    .fold(0, |bits, dy| bits | 1 << dy);
+                let c = char::from(63 + bits as u8);
+                if c != run.0 {
+                    push_run(&mut out, run);
+                    run = (c, 0);
+                }
+                run.1 += 1;
+            }
+            push_run(&mut out, run);
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+fn push_run(out: &mut String, (c, count): (char, usize)) {
+    match count {
+        0 => {}
+        1..=3 => out.extend(std::iter::repeat_n(c, count)),
+        _ => {
+            let _ = write!(out, "!{}{}", count, c);
+        }
+    }
+}
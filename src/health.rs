@@ -0,0 +1,61 @@
+use crate::feed::{FeedSource, HttpStatusError};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+#[derive(Clone, Serialize, Deserialize, Default)]
+pub struct FeedHealth {
+    #[serde(default)]
+    pub last_success: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub last_error: Option<String>,
+    #[serde(default)]
+    pub last_status: Option<u16>,
+    #[serde(default)]
+    pub consecutive_failures: u32,
+}
+
+impl FeedHealth {
+    pub fn load(source: &FeedSource) -> Self {
+        crate::cache::feed_state_path("health", source)
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_yaml::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, source: &FeedSource) {
+        if let Some(path) = crate::cache::feed_state_path("health", source) {
+            if let Some(parent) = path.parent() {
+                let _ = fs::create_dir_all(parent);
+            }
+            if let Ok(content) = serde_yaml::to_string(self) {
+                let _ = fs::write(path, content);
+            }
+        }
+    }
+
+    pub fn record_success(&mut self, status: u16) {
+        self.last_success = Some(Utc::now());
+        self.last_error = None;
+        self.last_status = Some(status);
+        self.consecutive_failures = 0;
+    }
+
+    pub fn record_failure(&mut self, error: &anyhow::Error) {
+        self.last_error = Some(format!("{:#}", error));
+        self.last_status = error
+            .downcast_ref::<HttpStatusError>()
+            .map(|e| e.0)
+            .or_else(|| {
+                error
+                    .downcast_ref::<reqwest::Error>()
+                    .and_then(|e| e.status())
+                    .map(|s| s.as_u16())
+            });
+        self.consecutive_failures += 1;
+    }
+
+    pub fn is_failing(&self) -> bool {
+        self.consecutive_failures > 0
+    }
+}
@@ -1,10 +1,19 @@
 mod app;
 mod cache;
 mod config;
+mod db;
+mod document;
 mod feed;
+mod graphics;
+mod health;
 mod likes;
+mod opml;
+mod query;
 mod reader;
 mod seen;
+mod smart_feed;
+#[cfg(test)]
+mod test_server;
 mod ui;
 
 use anyhow::Result;
@@ -17,6 +26,8 @@ use crossterm::{
 };
 use feed::FeedItem;
 use ratatui::{backend::CrosstermBackend, Terminal};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::mpsc;
 use std::{io, thread, time::Duration};
 
@@ -33,16 +44,38 @@ struct Cli {
 
 #[derive(Subcommand)]
 enum Commands {
+    /// Rebuild and install the latest version from GitHub
     Upgrade,
+    /// Import feeds from an OPML file into ~/.miam.yml
+    Import {
+        /// Path to the OPML file
+        file: PathBuf,
+    },
+    /// Fetch all feeds and update the cache without starting the TUI
+    Refresh,
+    /// Export feeds from ~/.miam.yml as OPML
+    Export {
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Apply the retention settings to the cache and compact it
+    Prune,
 }
 
 enum LoadResult {
     Items(Vec<FeedItem>, Option<String>),
     BackgroundUpdate(Vec<FeedItem>, Option<String>),
-    Article(reader::Article),
-    ArticleError(String),
+    Article(String, reader::Article),
+    ArticleError(String, String),
+    Image(String, Option<image::RgbImage>),
+    OfflineProgress(usize, usize),
+    OfflineDone(usize, usize),
+    FeedRefreshed(String, Vec<FeedItem>),
+    HealthUpdated(String, health::FeedHealth),
     FeedAdded(String, String),
     FeedAddError(String),
+    FeedsDiscovered(Vec<feed::DiscoveredFeed>),
 }
 
 fn main() -> Result<()> {
@@ -52,25 +85,44 @@ fn main() -> Result<()> {
         Some(Commands::Upgrade) => {
             return upgrade();
         }
+        Some(Commands::Import { file }) => {
+            return import_opml(&file);
+        }
+        Some(Commands::Export { output }) => {
+            return export_opml(output.as_deref());
+        }
+        Some(Commands::Refresh) => {
+            return refresh_headless();
+        }
+        Some(Commands::Prune) => {
+            return prune_cache();
+        }
         None => {}
     }
 
+    // Loading runs the one-time import of the old YAML files, so any warning
+    // about them prints before the screen is taken over
+    let mut app = App::new();
+    app.load_config();
+
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut app = App::new();
-    app.load_config();
-
     let (tx, rx) = mpsc::channel::<LoadResult>();
 
     if !app.sources.is_empty() {
         app.loading = true;
         app.status = "Loading all feeds...".to_string();
         app.current_feed = None;
-        spawn_refresh_all(app.sources.clone(), tx.clone());
+        spawn_refresh_all(
+            app.sources.clone(),
+            app.refresh_concurrency,
+            app.retention,
+            tx.clone(),
+        );
     }
 
     let res = run_app(&mut terminal, &mut app, rx, tx);
@@ -158,103 +210,459 @@ fn upgrade() -> Result<()> {
     Ok(())
 }
 
-fn spawn_refresh_all_cached(sources: Vec<feed::FeedSource>, tx: mpsc::Sender<LoadResult>) {
-    let cached = cache::load_all_cached();
+fn import_opml(path: &std::path::Path) -> Result<()> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+    let imported = opml::parse(&content)?;
+    let total = imported.len();
+
+    let mut config = match config::Config::load() {
+        Some(config) => config,
+        None if config::Config::exists() => {
+            anyhow::bail!("Could not parse ~/.miam.yml, fix it before importing")
+        }
+        None => config::Config::default(),
+    };
+    let added = opml::merge_sources(&mut config.sources, imported);
+    config.save();
+
+    println!("Imported {} feeds from {}", added, path.display());
+    if total > added {
+        println!("Skipped {} feeds already in your config", total - added);
+    }
+
+    Ok(())
+}
+
+fn export_opml(output: Option<&std::path::Path>) -> Result<()> {
+    let config = config::Config::load()
+        .ok_or_else(|| anyhow::anyhow!("No config found at ~/.miam.yml"))?;
+    let document = opml::render(&config.sources);
+
+    match output {
+        Some(path) => {
+            std::fs::write(path, document)
+                .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+            println!("Exported {} feeds to {}", config.sources.len(), path.display());
+        }
+        None => print!("{}", document),
+    }
+
+    Ok(())
+}
+
+fn refresh_headless() -> Result<()> {
+    let config = config::Config::load()
+        .ok_or_else(|| anyhow::anyhow!("No config found at ~/.miam.yml"))?;
+    cache::migrate_legacy_caches(&config.sources);
+
+    let failed = AtomicUsize::new(0);
+    refresh_concurrently(
+        &config.sources,
+        config.settings.refresh_concurrency,
+        config.settings.retention(),
+        |source, result| match result {
+            Ok((_, new_items)) => println!("✓ {}: {} new", source.name, new_items),
+            Err(e) => {
+                failed.fetch_add(1, Ordering::Relaxed);
+                println!("✗ {}: {:#}", source.name, e);
+            }
+        },
+    );
+    let failed = failed.into_inner();
+
+    println!(
+        "\nRefreshed {} feeds, {} failed",
+        config.sources.len() - failed,
+        failed
+    );
+
+    if failed > 0 {
+        anyhow::bail!("{} of {} feeds failed to refresh", failed, config.sources.len());
+    }
+
+    Ok(())
+}
+
+fn prune_cache() -> Result<()> {
+    let config = config::Config::load()
+        .ok_or_else(|| anyhow::anyhow!("No config found at ~/.miam.yml"))?;
+    cache::migrate_legacy_caches(&config.sources);
+
+    let stats = cache::prune(&config.sources, config.settings.retention())?;
+    println!(
+        "Removed {} cached items, {} saved articles and {} read markers",
+        stats.items, stats.articles, stats.seen
+    );
+    Ok(())
+}
+
+fn refresh_concurrently<F>(
+    sources: &[feed::FeedSource],
+    concurrency: usize,
+    retention: config::Retention,
+    on_result: F,
+) where
+    F: Fn(&feed::FeedSource, Result<(Vec<FeedItem>, usize)>) + Sync,
+{
+    run_concurrently(sources, concurrency, |source| {
+        on_result(source, refresh_source(source, retention))
+    });
+}
+
+fn run_concurrently<T, F>(jobs: &[T], concurrency: usize, run: F)
+where
+    T: Sync,
+    F: Fn(&T) + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let workers = concurrency.clamp(1, jobs.len().max(1));
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                while let Some(job) = jobs.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    run(job);
+                }
+            });
+        }
+    });
+}
+
+fn refresh_source(
+    source: &feed::FeedSource,
+    retention: config::Retention,
+) -> Result<(Vec<FeedItem>, usize)> {
+    let mut health = health::FeedHealth::load(source);
+    let result = fetch_and_merge(source, retention, &mut health);
+    if let Err(e) = &result {
+        health.record_failure(e);
+    }
+    health.save(source);
+    result
+}
+
+fn fetch_and_merge(
+    source: &feed::FeedSource,
+    retention: config::Retention,
+    health: &mut health::FeedHealth,
+) -> Result<(Vec<FeedItem>, usize)> {
+    // Only send validators when there is a cache to fall back on for a 304
+    let cached = cache::load_cached_items(source);
+    let validators = if cached.is_some() {
+        cache::load_validators(source)
+    } else {
+        feed::Validators::default()
+    };
+
+    match feed::fetch_feed_conditional(&source.url, &validators)? {
+        feed::FetchResult::NotModified => {
+            health.record_success(304);
+            Ok((cached.unwrap_or_default(), 0))
+        }
+        feed::FetchResult::Modified(feed_items, validators, status) => {
+            health.record_success(status.as_u16());
+            let items_for_source: Vec<_> = feed_items
+                .into_iter()
+                .map(|mut item| {
+                    item.source_name = source.name.clone();
+                    item
+                })
+                .collect();
+            let (merged, new_items) = cache::merge_and_save(source, items_for_source, retention);
+            cache::save_validators(source, &validators);
+            Ok((merged, new_items))
+        }
+    }
+}
+
+fn spawn_refresh_all_cached(
+    sources: Vec<feed::FeedSource>,
+    concurrency: usize,
+    retention: config::Retention,
+    tx: mpsc::Sender<LoadResult>,
+) {
+    let cached = cache::load_all_cached(&sources);
     if !cached.is_empty() {
         let _ = tx.send(LoadResult::Items(cached, None));
     }
 
     thread::spawn(move || {
-        for source in &sources {
-            if let Ok(feed_items) = feed::fetch_feed(&source.url) {
-                let items_for_source: Vec<_> = feed_items
-                    .into_iter()
-                    .map(|mut item| {
-                        item.source_name = source.name.clone();
-                        item
-                    })
-                    .collect();
-                cache::merge_and_save(&source.name, items_for_source);
+        refresh_concurrently(&sources, concurrency, retention, |source, result| {
+            let health = health::FeedHealth::load(source);
+            let _ = tx.send(LoadResult::HealthUpdated(source.name.clone(), health));
+            if let Ok((items, _)) = result {
+                let _ = tx.send(LoadResult::FeedRefreshed(source.name.clone(), items));
             }
-        }
-        let all_items = cache::load_all_cached();
+        });
+        let all_items = cache::load_all_cached(&sources);
         let _ = tx.send(LoadResult::BackgroundUpdate(all_items, None));
     });
 }
 
-fn spawn_refresh_single_cached(source: feed::FeedSource, tx: mpsc::Sender<LoadResult>) {
+fn spawn_refresh_single_cached(
+    source: feed::FeedSource,
+    retention: config::Retention,
+    tx: mpsc::Sender<LoadResult>,
+) {
     let source_name = source.name.clone();
-    if let Some(cached) = cache::load_cached_items(&source.name) {
+    if let Some(cached) = cache::load_cached_items(&source) {
         if !cached.is_empty() {
             let _ = tx.send(LoadResult::Items(cached, Some(source_name.clone())));
         }
     }
 
     thread::spawn(move || {
-        let mut items = Vec::new();
-        if let Ok(feed_items) = feed::fetch_feed(&source.url) {
-            for mut item in feed_items {
-                item.source_name = source.name.clone();
-                items.push(item);
-            }
-        }
-        items.sort_by(|a, b| b.date.cmp(&a.date));
-        let merged = cache::merge_and_save(&source.name, items);
+        let merged = match refresh_source(&source, retention) {
+            Ok((items, _)) => items,
+            Err(_) => cache::load_cached_items(&source).unwrap_or_default(),
+        };
+        let health = health::FeedHealth::load(&source);
+        let _ = tx.send(LoadResult::HealthUpdated(source.name.clone(), health));
         let _ = tx.send(LoadResult::BackgroundUpdate(merged, Some(source_name)));
     });
 }
 
-fn spawn_refresh_all(sources: Vec<feed::FeedSource>, tx: mpsc::Sender<LoadResult>) {
-    spawn_refresh_all_cached(sources, tx);
+fn spawn_refresh_all(
+    sources: Vec<feed::FeedSource>,
+    concurrency: usize,
+    retention: config::Retention,
+    tx: mpsc::Sender<LoadResult>,
+) {
+    spawn_refresh_all_cached(sources, concurrency, retention, tx);
+}
+
+fn spawn_refresh_single(
+    source: feed::FeedSource,
+    retention: config::Retention,
+    tx: mpsc::Sender<LoadResult>,
+) {
+    spawn_refresh_single_cached(source, retention, tx);
+}
+
+/// Shows the selected Feeds panel entry. A source is refreshed; a smart
+/// feed covers every source, so it is shown from the cache and only
+/// refreshed when asked to with `r`.
+fn open_sidebar_entry(app: &mut App, refresh: bool, tx: &mpsc::Sender<LoadResult>) {
+    let verb = if refresh { "Refreshing" } else { "Loading" };
+    match app.selected_entry() {
+        Some(app::SidebarEntry::Smart(idx)) => {
+            app.current_feed = None;
+            app.search_query = None;
+            app.smart_feed = Some(idx);
+            if refresh {
+                app.loading = true;
+                app.status = format!("{} {}...", verb, app.smart_feeds[idx].name);
+                spawn_refresh_all(
+                    app.sources.clone(),
+                    app.refresh_concurrency,
+                    app.retention,
+                    tx.clone(),
+                );
+            } else {
+                app.show_cached_items();
+                app.status = format!("Showing {}", app.smart_feeds[idx].name);
+            }
+        }
+        Some(app::SidebarEntry::Source(idx)) => {
+            let source = app.sources[idx].clone();
+            app.loading = true;
+            app.status = format!("{} {}...", verb, source.name);
+            app.current_feed = Some(source.name.clone());
+            app.search_query = None;
+            app.smart_feed = None;
+            spawn_refresh_single(source, app.retention, tx.clone());
+        }
+        None => {}
+    }
+}
+
+fn open_selected_in_reader(app: &mut App, tx: &mpsc::Sender<LoadResult>) {
+    if let Some(item) = app.get_selected_item() {
+        let url = item.link.clone().unwrap_or_default();
+        let identifier = seen::get_item_identifier(&item.link, &item.title);
+        let fallback = reader::article_from_feed_item(item);
+        app.mark_selected_as_seen();
+        app.reader_history.clear();
+        app.reader_item = Some(identifier.clone());
+        if let Some(article) = cache::load_article(&identifier) {
+            app.show_article(article);
+        } else {
+            app.article_loading = true;
+            app.status = "Loading article...".to_string();
+            spawn_fetch_article(url, identifier, app.paywall_remover, fallback, tx.clone());
+        }
+
+        if let Some((identifier, url)) = app.next_readable_item() {
+            if !cache::has_article(&identifier) {
+                spawn_prefetch_article(url, identifier, app.paywall_remover);
+            }
+        }
+    }
+}
+
+// Loads a link from the article into the reader, keeping the article to go
+// back to.
+fn follow_link(app: &mut App, url: String, tx: &mpsc::Sender<LoadResult>) {
+    app.push_reader_page(url.clone());
+    if let Some(article) = cache::load_article(&url) {
+        app.show_article(article);
+    } else {
+        app.article_loading = true;
+        spawn_fetch_article(url.clone(), url, app.paywall_remover, None, tx.clone());
+    }
+}
+
+fn read_adjacent(app: &mut App, forward: bool, tx: &mpsc::Sender<LoadResult>) {
+    if app.select_adjacent_readable(forward) {
+        open_selected_in_reader(app, tx);
+    }
 }
 
-fn spawn_refresh_single(source: feed::FeedSource, tx: mpsc::Sender<LoadResult>) {
-    spawn_refresh_single_cached(source, tx);
+// Moves the reader on to the next unread item. Items the reader can't show,
+// like videos, are left selected in the list instead.
+fn read_next_unread(app: &mut App, tx: &mpsc::Sender<LoadResult>) {
+    if !app.next_unread() {
+        return;
+    }
+    if app.can_open_in_reader() {
+        open_selected_in_reader(app, tx);
+    } else {
+        app.close_reader();
+    }
 }
 
-fn spawn_fetch_article(url: String, paywall_remover: bool, tx: mpsc::Sender<LoadResult>) {
+fn spawn_fetch_article(
+    url: String,
+    identifier: String,
+    paywall_remover: bool,
+    fallback: Option<reader::Article>,
+    tx: mpsc::Sender<LoadResult>,
+) {
     thread::spawn(move || {
-        match reader::fetch_article(&url, paywall_remover) {
-            Ok(article) => {
-                let _ = tx.send(LoadResult::Article(article));
+        // Fall back to the content shipped in the feed when the page can't be fetched
+        match (reader::fetch_article(&url, paywall_remover), fallback) {
+            (Ok(article), _) => {
+                cache::save_article(&identifier, &article);
+                let _ = tx.send(LoadResult::Article(identifier, article));
             }
-            Err(e) => {
-                let _ = tx.send(LoadResult::ArticleError(e.to_string()));
+            (Err(_), Some(article)) => {
+                let _ = tx.send(LoadResult::Article(identifier, article));
+            }
+            (Err(e), None) => {
+                let _ = tx.send(LoadResult::ArticleError(identifier, e.to_string()));
             }
         }
     });
 }
 
+fn spawn_fetch_images(sources: Vec<String>, tx: mpsc::Sender<LoadResult>) {
+    thread::spawn(move || {
+        run_concurrently(&sources, 4, |src| {
+            let image = reader::fetch_image(src).ok();
+            let _ = tx.send(LoadResult::Image(src.clone(), image));
+        });
+    });
+}
+
+// Fetches an article into the cache so opening it later is instant.
+fn spawn_prefetch_article(url: String, identifier: String, paywall_remover: bool) {
+    thread::spawn(move || {
+        if let Ok(article) = reader::fetch_article(&url, paywall_remover) {
+            cache::save_article(&identifier, &article);
+        }
+    });
+}
+
+fn spawn_download_offline(
+    articles: Vec<(String, String)>,
+    paywall_remover: bool,
+    concurrency: usize,
+    tx: mpsc::Sender<LoadResult>,
+) {
+    thread::spawn(move || {
+        let total = articles.len();
+        let done = AtomicUsize::new(0);
+        let failed = AtomicUsize::new(0);
+        run_concurrently(&articles, concurrency, |(identifier, url)| {
+            match reader::fetch_article(url, paywall_remover) {
+                Ok(article) => cache::save_article(identifier, &article),
+                Err(_) => {
+                    failed.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+            let _ = tx.send(LoadResult::OfflineProgress(done, total));
+        });
+        let _ = tx.send(LoadResult::OfflineDone(total, failed.into_inner()));
+    });
+}
+
 fn spawn_add_feed(url: String, tx: mpsc::Sender<LoadResult>) {
     thread::spawn(move || {
-        match feed::fetch_feed(&url) {
-            Ok(items) => {
-                let name = items
-                    .first()
-                    .map(|i| i.source_name.clone())
-                    .unwrap_or_else(|| "Unknown".to_string());
-                let _ = tx.send(LoadResult::FeedAdded(url, name));
+        // A page that isn't a feed itself comes back with the feeds found for it
+        let mut discovered = match feed::resolve(&url) {
+            Ok(feed::Resolved::Feed(items)) => {
+                let _ = tx.send(LoadResult::FeedAdded(url, feed_name(&items, None)));
+                return;
             }
-            Err(_) => {
+            Ok(feed::Resolved::Page(discovered)) => discovered,
+            Err(_) => Vec::new(),
+        };
+        match discovered.len() {
+            0 => {
                 let _ = tx.send(LoadResult::FeedAddError(url));
             }
+            1 => {
+                let found = discovered.remove(0);
+                match feed::fetch_feed(&found.url) {
+                    Ok(items) => {
+                        let name = feed_name(&items, found.title);
+                        let _ = tx.send(LoadResult::FeedAdded(found.url, name));
+                    }
+                    Err(_) => {
+                        let _ = tx.send(LoadResult::FeedAddError(found.url));
+                    }
+                }
+            }
+            _ => {
+                let _ = tx.send(LoadResult::FeedsDiscovered(discovered));
+            }
         }
     });
 }
 
+fn feed_name(items: &[FeedItem], fallback: Option<String>) -> String {
+    items
+        .first()
+        .map(|i| i.source_name.clone())
+        .filter(|name| !name.is_empty())
+        .or(fallback)
+        .unwrap_or_else(|| "Unknown".to_string())
+}
+
 fn run_app<B: ratatui::backend::Backend>(
     terminal: &mut Terminal<B>,
     app: &mut App,
     rx: mpsc::Receiver<LoadResult>,
     tx: mpsc::Sender<LoadResult>,
 ) -> Result<()> {
+    let mut graphics = graphics::Graphics::default();
     loop {
-        terminal.draw(|f| ui::render(f, app))?;
+        let mut layout = ui::ReaderLayout::default();
+        let screen = terminal.draw(|f| layout = ui::render(f, app))?.area;
+        graphics.show(app.image_output, layout.placements, screen, &app.article_images)?;
+        app.update_reader_matches(layout.matches);
+
+        // However the article got into the reader, its images load from here
+        let sources = app.request_article_images();
+        if !sources.is_empty() {
+            spawn_fetch_images(sources, tx.clone());
+        }
 
-        if let Ok(result) = rx.try_recv() {
+        while let Ok(result) = rx.try_recv() {
             match result {
                 LoadResult::Items(items, feed_name) => {
-                    if app.current_feed == feed_name {
+                    if app.current_feed == feed_name && app.search_query.is_none() {
                         app.items = items;
                         app.update_items_seen_status();
                         app.update_items_like_status();
@@ -266,7 +674,14 @@ fn run_app<B: ratatui::backend::Backend>(
                     }
                 }
                 LoadResult::BackgroundUpdate(items, feed_name) => {
-                    if app.current_feed == feed_name {
+                    match &feed_name {
+                        Some(name) => app.update_cached_feed(name, &items),
+                        None => app.reload_unread_counts(),
+                    }
+                    // The refresh is over even when the view has moved on,
+                    // and keys are ignored while loading
+                    app.loading = false;
+                    if app.current_feed == feed_name && app.search_query.is_none() {
                         let old_count = app.items.len();
                         app.items = items;
                         app.update_items_seen_status();
@@ -282,12 +697,48 @@ fn run_app<B: ratatui::backend::Backend>(
                         app.background_loading = false;
                     }
                 }
-                LoadResult::Article(article) => {
-                    app.show_article(article);
+                LoadResult::FeedRefreshed(feed_name, items) => {
+                    app.update_cached_feed(&feed_name, &items);
+                    if app.current_feed.is_none() && app.search_query.is_none() {
+                        app.merge_feed_items(&feed_name, items);
+                        if app.loading {
+                            app.loading = false;
+                            app.background_loading = true;
+                        }
+                    }
+                }
+                LoadResult::HealthUpdated(feed_name, health) => {
+                    app.feed_health.insert(feed_name, health);
+                }
+                // Ignore articles the reader has already moved away from
+                LoadResult::Article(identifier, article) => {
+                    if app.reader_item.as_ref() == Some(&identifier) {
+                        app.show_article(article);
+                    }
+                }
+                LoadResult::ArticleError(identifier, err) => {
+                    if app.reader_item.as_ref() == Some(&identifier) {
+                        app.article_loading = false;
+                        app.status = format!("Failed to load article: {}", err);
+                        // A followed link that failed goes back to its article
+                        app.reader_back();
+                    }
+                }
+                LoadResult::Image(src, image) => {
+                    if let Some(slot) = app.article_images.get_mut(&src) {
+                        *slot = image;
+                    }
                 }
-                LoadResult::ArticleError(err) => {
-                    app.article_loading = false;
-                    app.status = format!("Failed to load article: {}", err);
+                LoadResult::OfflineProgress(done, total) => {
+                    app.status = format!("Downloading for offline: {}/{}", done, total);
+                }
+                LoadResult::OfflineDone(total, failed) => {
+                    app.offline_downloading = false;
+                    app.status = if failed > 0 {
+                        format!("Saved {} articles for offline ({} failed)", total - failed, failed)
+                    } else {
+                        format!("Saved {} articles for offline", total)
+                    };
                 }
                 LoadResult::FeedAdded(url, name) => {
                     app.add_feed_source(url, name);
@@ -298,6 +749,10 @@ fn run_app<B: ratatui::backend::Backend>(
                     app.loading = false;
                     app.status = format!("Failed to fetch feed: {}", url);
                 }
+                LoadResult::FeedsDiscovered(feeds) => {
+                    app.loading = false;
+                    app.start_feed_picker(feeds);
+                }
             }
         }
 
@@ -315,11 +770,49 @@ fn run_app<B: ratatui::backend::Backend>(
                     continue;
                 }
 
+                if app.link_hint_mode {
+                    match key.code {
+                        KeyCode::Char(c) if c.is_ascii_digit() => app.push_link_hint_digit(c),
+                        KeyCode::Backspace => {
+                            app.link_hint_input.pop();
+                        }
+                        KeyCode::Enter => {
+                            if let Some(url) = app.take_link_hint() {
+                                follow_link(app, url, &tx);
+                            }
+                        }
+                        KeyCode::Char('o') => {
+                            if let Some(url) = app.take_link_hint() {
+                                app.open_url(&url);
+                            }
+                        }
+                        KeyCode::Esc => app.cancel_link_hint(),
+                        _ => {}
+                    }
+                    continue;
+                }
+
+                if app.reader_search_mode {
+                    match key.code {
+                        KeyCode::Enter => app.submit_reader_search(),
+                        KeyCode::Esc => app.cancel_reader_search(),
+                        KeyCode::Char(c) => app.reader_search_input.push(c),
+                        KeyCode::Backspace => {
+                            app.reader_search_input.pop();
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+
                 if app.focus == app::Focus::Reader {
                     match key.code {
-                        KeyCode::Esc | KeyCode::Char('q') => app.close_reader(),
+                        KeyCode::Esc if app.reader_search.is_some() => app.clear_reader_search(),
+                        KeyCode::Esc | KeyCode::Char('q') => app.leave_reader_page(),
                         KeyCode::Char('j') | KeyCode::Down => app.scroll_article_down(),
                         KeyCode::Char('k') | KeyCode::Up => app.scroll_article_up(),
+                        KeyCode::Char('l') => app.scroll_article_right(8),
+                        KeyCode::Char('h') => app.scroll_article_left(8),
                         KeyCode::Char(' ') | KeyCode::PageDown => app.scroll_article_page_down(20),
                         KeyCode::Char('b') | KeyCode::PageUp => app.scroll_article_page_up(20),
                         KeyCode::Char('g') => {
@@ -329,10 +822,84 @@ fn run_app<B: ratatui::backend::Backend>(
                             app.article_scroll = u16::MAX;
                         }
                         KeyCode::Char('o') => {
-                            if let Some(url) = app.get_selected_url() {
+                            if let Some(url) = app.reader_url() {
                                 app.open_url(&url);
                             }
                         }
+                        KeyCode::Char('f') => app.start_link_hint(),
+                        KeyCode::Char('/') => app.start_reader_search(),
+                        // While searching, n and N move between matches
+                        KeyCode::Char('n') if app.reader_search.is_some() => app.next_reader_match(),
+                        KeyCode::Char('N') if app.reader_search.is_some() => app.previous_reader_match(),
+                        KeyCode::Char('n') => read_next_unread(app, &tx),
+                        KeyCode::Char('J') | KeyCode::Right => read_adjacent(app, true, &tx),
+                        KeyCode::Char('K') | KeyCode::Left => read_adjacent(app, false, &tx),
+                        _ => {}
+                    }
+                    continue;
+                }
+
+                if app.show_health {
+                    if matches!(key.code, KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('H')) {
+                        app.toggle_health();
+                    }
+                    continue;
+                }
+
+                if app.feed_picker_mode {
+                    match key.code {
+                        KeyCode::Char('j') | KeyCode::Down => app.next_discovered_feed(),
+                        KeyCode::Char('k') | KeyCode::Up => app.previous_discovered_feed(),
+                        KeyCode::Enter => {
+                            if let Some(url) = app.submit_feed_picker() {
+                                app.loading = true;
+                                app.status = "Adding feed...".to_string();
+                                spawn_add_feed(url, tx.clone());
+                            }
+                        }
+                        KeyCode::Esc | KeyCode::Char('q') => app.cancel_feed_picker(),
+                        _ => {}
+                    }
+                    continue;
+                }
+
+                if app.search_mode {
+                    match key.code {
+                        KeyCode::Enter => app.submit_search(),
+                        KeyCode::Esc => app.cancel_search(),
+                        KeyCode::Char(c) => app.search_input.push(c),
+                        KeyCode::Backspace => {
+                            app.search_input.pop();
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+
+                if app.mark_older_mode {
+                    match key.code {
+                        KeyCode::Enter => app.submit_mark_older(),
+                        KeyCode::Esc => app.cancel_mark_older(),
+                        KeyCode::Char(c) if c.is_ascii_digit() => app.mark_older_input.push(c),
+                        KeyCode::Backspace => {
+                            app.mark_older_input.pop();
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+
+                if app.edit_feed_mode {
+                    match key.code {
+                        KeyCode::Enter => app.submit_edit_feed(),
+                        KeyCode::Esc => app.cancel_edit_feed(),
+                        KeyCode::Tab | KeyCode::BackTab | KeyCode::Up | KeyCode::Down => {
+                            app.toggle_edit_field()
+                        }
+                        KeyCode::Char(c) => app.edit_input_mut().push(c),
+                        KeyCode::Backspace => {
+                            app.edit_input_mut().pop();
+                        }
                         _ => {}
                     }
                     continue;
@@ -366,15 +933,11 @@ fn run_app<B: ratatui::backend::Backend>(
                         KeyCode::BackTab => {
                             app.previous_tag();
                         }
-                        KeyCode::Left | KeyCode::Up => {
-                            if app.tag_input.is_empty() {
-                                app.previous_tag();
-                            }
+                        KeyCode::Left | KeyCode::Up if app.tag_input.is_empty() => {
+                            app.previous_tag();
                         }
-                        KeyCode::Right | KeyCode::Down => {
-                            if app.tag_input.is_empty() {
-                                app.next_tag();
-                            }
+                        KeyCode::Right | KeyCode::Down if app.tag_input.is_empty() => {
+                            app.next_tag();
                         }
                         _ => {}
                     }
@@ -383,37 +946,8 @@ fn run_app<B: ratatui::backend::Backend>(
                         KeyCode::Enter => {
                             app.exit_filter();
                             if app.focus == app::Focus::Feeds && !app.sources.is_empty() {
-                                let mut offset = 0;
-                                if app.show_all && app.filter.is_empty() {
-                                    offset += 1;
-                                }
-                                if app.filter.is_empty() {
-                                    offset += 1;
-                                    offset += 1;
-                                    offset += 1;
-                                }
-
-                                if app.show_all && app.feed_index == 0 && app.filter.is_empty() {
-                                    app.loading = true;
-                                    app.status = "Loading all feeds...".to_string();
-                                    app.current_feed = None;
-                                    spawn_refresh_all(app.sources.clone(), tx.clone());
-                                    app.focus = app::Focus::Items;
-                                } else {
-                                    let filtered_sources = app.get_filtered_sources();
-                                    let display_idx = app.feed_index - offset;
-                                    if display_idx < filtered_sources.len() {
-                                        let (original_idx, _) = filtered_sources[display_idx];
-                                        let source = app.sources[original_idx].clone();
-                                        app.loading = true;
-                                        app.status = format!("Loading {}...", source.name);
-                                        app.current_feed = Some(source.name.clone());
-                                        spawn_refresh_single(source, tx.clone());
-                                        app.focus = app::Focus::Items;
-                                    } else {
-                                        app.status = format!("Error: Invalid feed index {} (max {})", display_idx, filtered_sources.len());
-                                    }
-                                }
+                                open_sidebar_entry(app, false, &tx);
+                                app.focus = app::Focus::Items;
                             } else if app.focus == app::Focus::Tags {
                                 app.select_tag();
                             } else if app.focus == app::Focus::Items {
@@ -421,58 +955,6 @@ fn run_app<B: ratatui::backend::Backend>(
                             }
                         }
                         KeyCode::Esc => app.clear_filter(),
-                        KeyCode::Char('r') => {
-                            app.exit_filter();
-                            if !app.sources.is_empty() {
-                                if app.focus == app::Focus::Items && app.current_feed.is_some() {
-                                    let feed_name = app.current_feed.clone().unwrap();
-                                    if let Some(source) = app.sources.iter().find(|s| s.name == feed_name).cloned() {
-                                        app.loading = true;
-                                        app.status = format!("Refreshing {}...", source.name);
-                                        spawn_refresh_single(source, tx.clone());
-                                    } else {
-                                        app.status = "Cannot refresh: feed not found".to_string();
-                                    }
-                                } else if app.focus == app::Focus::Items && app.current_feed.is_none() {
-                                    app.loading = true;
-                                    app.status = "Refreshing all feeds...".to_string();
-                                    spawn_refresh_all(app.sources.clone(), tx.clone());
-                                } else {
-                                    let mut offset = 0;
-                                    if app.show_all && app.filter.is_empty() {
-                                        offset += 1;
-                                    }
-                                    let liked_index = offset;
-                                    if app.filter.is_empty() {
-                                        offset += 1;
-                                        offset += 1;
-                                        offset += 1;
-                                    }
-
-                                    if app.show_all && app.feed_index == 0 && app.filter.is_empty() {
-                                        app.loading = true;
-                                        app.status = "Refreshing all feeds...".to_string();
-                                        app.current_feed = None;
-                                        spawn_refresh_all(app.sources.clone(), tx.clone());
-                                    } else if (app.feed_index == liked_index || app.feed_index == liked_index + 1 || app.feed_index == liked_index + 2) && app.filter.is_empty() {
-                                        app.status = "Special feeds don't need refresh".to_string();
-                                    } else {
-                                        let filtered_sources = app.get_filtered_sources();
-                                        let display_idx = app.feed_index - offset;
-                                        if display_idx < filtered_sources.len() {
-                                            let (original_idx, _) = filtered_sources[display_idx];
-                                            let source = app.sources[original_idx].clone();
-                                            app.loading = true;
-                                            app.status = format!("Refreshing {}...", source.name);
-                                            app.current_feed = Some(source.name.clone());
-                                            spawn_refresh_single(source, tx.clone());
-                                        } else {
-                                            app.status = format!("Error: Invalid feed index {} (max {})", display_idx, filtered_sources.len());
-                                        }
-                                    }
-                                }
-                            }
-                        }
                         KeyCode::Char(c) => {
                             app.filter.push(c);
                             match app.focus {
@@ -538,18 +1020,17 @@ fn run_app<B: ratatui::backend::Backend>(
 
                     match key.code {
                         KeyCode::Char('q') => return Ok(()),
-                        KeyCode::Char('/') => {
+                        KeyCode::Char('/')
                             if app.focus == app::Focus::Feeds
                                 || app.focus == app::Focus::Items
-                                || app.focus == app::Focus::Tags
-                            {
-                                app.start_filter();
-                            }
+                                || app.focus == app::Focus::Tags =>
+                        {
+                            app.start_filter();
                         }
-                        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::ALT) => {
-                            if !app.filter.is_empty() {
-                                app.clear_filter();
-                            }
+                        KeyCode::Char('c')
+                            if key.modifiers.contains(KeyModifiers::ALT) && !app.filter.is_empty() =>
+                        {
+                            app.clear_filter();
                         }
                         KeyCode::Char('j') | KeyCode::Down => app.next(),
                         KeyCode::Char('k') | KeyCode::Up => app.previous(),
@@ -559,148 +1040,94 @@ fn run_app<B: ratatui::backend::Backend>(
                         KeyCode::BackTab => app.toggle_focus(),
                         KeyCode::Enter => {
                             if app.focus == app::Focus::Feeds && !app.sources.is_empty() {
-                                let mut offset = 0;
-                                if app.show_all && app.filter.is_empty() {
-                                    offset += 1;
-                                }
-                                let liked_index = offset;
-                                if app.filter.is_empty() {
-                                    offset += 1;
-                                    offset += 1;
-                                    offset += 1;
-                                }
-
-                                if app.show_all && app.feed_index == 0 && app.filter.is_empty() {
-                                    app.loading = true;
-                                    app.status = "Loading all feeds...".to_string();
-                                    app.current_feed = None;
-                                    app.show_liked = false;
-                                    app.show_articles = false;
-                                    app.show_videos = false;
-                                    spawn_refresh_all(app.sources.clone(), tx.clone());
-                                    app.focus = app::Focus::Items;
-                                } else if app.feed_index == liked_index && app.filter.is_empty() {
-                                    app.show_liked = true;
-                                    app.show_articles = false;
-                                    app.show_videos = false;
-                                    app.status = "Showing liked items".to_string();
-                                    app.current_feed = None;
-                                    app.load_liked_items();
-                                    app.focus = app::Focus::Items;
-                                } else if app.feed_index == liked_index + 1 && app.filter.is_empty() {
-                                    app.show_liked = false;
-                                    app.show_articles = true;
-                                    app.show_videos = false;
-                                    app.status = "Showing articles only".to_string();
-                                    app.current_feed = None;
-                                    app.load_liked_items();
-                                    app.focus = app::Focus::Items;
-                                } else if app.feed_index == liked_index + 2 && app.filter.is_empty() {
-                                    app.show_liked = false;
-                                    app.show_articles = false;
-                                    app.show_videos = true;
-                                    app.status = "Showing videos only".to_string();
-                                    app.current_feed = None;
-                                    app.load_liked_items();
-                                    app.focus = app::Focus::Items;
-                                } else {
-                                    let filtered_sources = app.get_filtered_sources();
-                                    let display_idx = app.feed_index - offset;
-                                    if display_idx < filtered_sources.len() {
-                                        let (original_idx, _) = filtered_sources[display_idx];
-                                        let source = app.sources[original_idx].clone();
-                                        app.loading = true;
-                                        app.status = format!("Loading {}...", source.name);
-                                        app.current_feed = Some(source.name.clone());
-                                        app.show_liked = false;
-                                        app.show_articles = false;
-                                        app.show_videos = false;
-                                        spawn_refresh_single(source, tx.clone());
-                                        app.focus = app::Focus::Items;
-                                    } else {
-                                        app.status = format!("Error: Invalid feed index {} (max {})", display_idx, filtered_sources.len());
-                                    }
-                                }
+                                open_sidebar_entry(app, false, &tx);
+                                app.focus = app::Focus::Items;
                             } else if app.focus == app::Focus::Tags {
                                 app.select_tag();
                             } else if app.focus == app::Focus::Items {
                                 app.open_selected();
                             }
                         }
-                        KeyCode::Char('t') => {
-                            if app.focus == app::Focus::Feeds {
-                                app.start_tag_editor();
-                            }
+                        KeyCode::Char('t') if app.focus == app::Focus::Feeds => {
+                            app.start_tag_editor();
+                        }
+                        KeyCode::Char('e') if app.focus == app::Focus::Feeds => {
+                            app.start_edit_feed();
                         }
                         KeyCode::Char('a') => app.start_add_feed(),
+                        KeyCode::Char('s') => app.start_search(),
+                        KeyCode::Char('H') => app.toggle_health(),
+                        KeyCode::Char('D') if !app.offline_downloading => {
+                            let articles = app.get_articles_to_download();
+                            if articles.is_empty() {
+                                app.status = "All unread articles are already available offline".to_string();
+                            } else {
+                                app.offline_downloading = true;
+                                app.status = format!("Downloading {} articles for offline...", articles.len());
+                                spawn_download_offline(
+                                    articles,
+                                    app.paywall_remover,
+                                    app.refresh_concurrency,
+                                    tx.clone(),
+                                );
+                            }
+                        }
                         KeyCode::Char('d') => app.delete_selected(),
-                        KeyCode::Char('r') => {
-                            if !app.sources.is_empty() {
-                                if app.focus == app::Focus::Items && app.current_feed.is_some() {
-                                    let feed_name = app.current_feed.clone().unwrap();
-                                    if let Some(source) = app.sources.iter().find(|s| s.name == feed_name).cloned() {
-                                        app.loading = true;
-                                        app.status = format!("Refreshing {}...", source.name);
-                                        spawn_refresh_single(source, tx.clone());
-                                    } else {
-                                        app.status = "Cannot refresh: feed not found".to_string();
-                                    }
-                                } else if app.focus == app::Focus::Items && app.current_feed.is_none() {
+                        KeyCode::Char('r') if !app.sources.is_empty() => {
+                            // Search results aren't refreshed, so go back to the
+                            // view the search was made from
+                            app.search_query = None;
+                            if app.focus == app::Focus::Items && app.current_feed.is_some() {
+                                let feed_name = app.current_feed.clone().unwrap();
+                                if let Some(source) = app.sources.iter().find(|s| s.name == feed_name).cloned() {
                                     app.loading = true;
-                                    app.status = "Refreshing all feeds...".to_string();
-                                    spawn_refresh_all(app.sources.clone(), tx.clone());
+                                    app.status = format!("Refreshing {}...", source.name);
+                                    spawn_refresh_single(source, app.retention, tx.clone());
                                 } else {
-                                    let mut offset = 0;
-                                    if app.show_all && app.filter.is_empty() {
-                                        offset += 1;
-                                    }
-                                    let liked_index = offset;
-                                    if app.filter.is_empty() {
-                                        offset += 1;
-                                        offset += 1;
-                                        offset += 1;
-                                    }
-
-                                    if app.show_all && app.feed_index == 0 && app.filter.is_empty() {
-                                        app.loading = true;
-                                        app.status = "Refreshing all feeds...".to_string();
-                                        app.current_feed = None;
-                                        spawn_refresh_all(app.sources.clone(), tx.clone());
-                                    } else if (app.feed_index == liked_index || app.feed_index == liked_index + 1 || app.feed_index == liked_index + 2) && app.filter.is_empty() {
-                                        app.status = "Special feeds don't need refresh".to_string();
-                                    } else {
-                                        let filtered_sources = app.get_filtered_sources();
-                                        let display_idx = app.feed_index - offset;
-                                        if display_idx < filtered_sources.len() {
-                                            let (original_idx, _) = filtered_sources[display_idx];
-                                            let source = app.sources[original_idx].clone();
-                                            app.loading = true;
-                                            app.status = format!("Refreshing {}...", source.name);
-                                            app.current_feed = Some(source.name.clone());
-                                            spawn_refresh_single(source, tx.clone());
-                                        } else {
-                                            app.status = format!("Error: Invalid feed index {} (max {})", display_idx, filtered_sources.len());
-                                        }
-                                    }
+                                    app.status = "Cannot refresh: feed not found".to_string();
                                 }
+                            } else if app.focus == app::Focus::Items && app.current_feed.is_none() {
+                                app.loading = true;
+                                app.status = "Refreshing all feeds...".to_string();
+                                spawn_refresh_all(
+                                    app.sources.clone(),
+                                    app.refresh_concurrency,
+                                    app.retention,
+                                    tx.clone(),
+                                );
+                            } else {
+                                open_sidebar_entry(app, true, &tx);
                             }
                         }
                         KeyCode::Char('o') => {
                             if app.focus == app::Focus::Items && app.can_open_in_reader() {
-                                if let Some(url) = app.get_selected_url() {
-                                    app.mark_selected_as_seen();
-                                    app.article_loading = true;
-                                    app.status = "Loading article...".to_string();
-                                    spawn_fetch_article(url, app.paywall_remover, tx.clone());
-                                }
+                                open_selected_in_reader(app, &tx);
                             } else {
                                 app.open_selected();
                             }
                         }
-                        KeyCode::Char('l') => {
-                            if app.focus == app::Focus::Items {
-                                app.toggle_selected_like();
-                            }
+                        KeyCode::Char('l') if app.focus == app::Focus::Items => {
+                            app.toggle_selected_like();
+                        }
+                        KeyCode::Char('m') if app.focus == app::Focus::Items => {
+                            app.toggle_selected_seen();
+                        }
+                        KeyCode::Char('M') if app.focus == app::Focus::Items => {
+                            app.mark_view_as_seen();
+                        }
+                        KeyCode::Char('M') if app.focus == app::Focus::Feeds => {
+                            app.mark_selected_feed_as_seen();
+                        }
+                        KeyCode::Char('O') => app.start_mark_older(),
+                        KeyCode::Char('u') => app.toggle_hide_read(),
+                        KeyCode::Char('n') if app.focus == app::Focus::Items => {
+                            app.next_unread();
+                        }
+                        KeyCode::Char('N') if app.focus == app::Focus::Items => {
+                            app.previous_unread();
+                        }
+                        KeyCode::Char('J') => {
+                            app.next_unread_feed();
                         }
                         _ => {}
                     }
@@ -709,3 +1136,82 @@ fn run_app<B: ratatui::backend::Backend>(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{self, Response};
+    use std::sync::Mutex;
+    use std::time::Instant;
+
+    fn source(name: &str, url: String) -> feed::FeedSource {
+        feed::FeedSource {
+            name: name.to_string(),
+            url,
+            tags: Vec::new(),
+            id: None,
+            max_items: None,
+            max_age_days: None,
+        }
+    }
+
+    #[test]
+    fn slow_feed_holds_up_one_worker_and_failures_are_reported() {
+        test_server::isolate_home();
+        let base = test_server::serve(|request| match request.path.as_str() {
+            "/slow.xml" => Response::ok("application/rss+xml", &test_server::rss("Slow", &["s1"]))
+                .delay(Duration::from_millis(600)),
+            "/broken.xml" => Response::status(500),
+            path => Response::ok("application/rss+xml", &test_server::rss(path, &["one", "two"])),
+        });
+        let sources: Vec<_> = ["slow", "a", "broken", "b"]
+            .iter()
+            .map(|name| source(name, format!("{}/{}.xml", base, name)))
+            .collect();
+
+        let finished = Mutex::new(Vec::new());
+        let started = Instant::now();
+        refresh_concurrently(&sources, 2, config::Retention::default(), |source, result| {
+            let outcome = result.map(|(items, new)| (items.len(), new)).ok();
+            finished.lock().unwrap().push((source.name.clone(), outcome));
+        });
+
+        assert!(started.elapsed() < Duration::from_millis(1200));
+        let finished = finished.into_inner().unwrap();
+        assert_eq!(finished.last().unwrap(), &("slow".to_string(), Some((1, 1))));
+        assert!(finished.contains(&("a".to_string(), Some((2, 2)))));
+        assert!(finished.contains(&("b".to_string(), Some((2, 2)))));
+        assert!(finished.contains(&("broken".to_string(), None)));
+
+        let health = health::FeedHealth::load(&sources[2]);
+        assert_eq!(health.last_status, Some(500));
+        assert_eq!(health.consecutive_failures, 1);
+        assert_eq!(health::FeedHealth::load(&sources[1]).last_status, Some(200));
+    }
+
+    #[test]
+    fn run_concurrently_runs_every_job_once_within_the_limit() {
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let done = Mutex::new(Vec::new());
+        let jobs: Vec<usize> = (0..12).collect();
+
+        run_concurrently(&jobs, 3, |job| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(20));
+            running.fetch_sub(1, Ordering::SeqCst);
+            done.lock().unwrap().push(*job);
+        });
+
+        let mut done = done.into_inner().unwrap();
+        done.sort();
+        assert_eq!(done, jobs);
+        assert!(peak.load(Ordering::SeqCst) <= 3);
+    }
+
+    #[test]
+    fn run_concurrently_handles_no_jobs() {
+        run_concurrently(&[] as &[usize], 4, |_| panic!("no job to run"));
+    }
+}
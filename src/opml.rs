@@ -0,0 +1,262 @@
+use crate::feed::{url_key, FeedSource};
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+
+pub fn parse(content: &str) -> Result<Vec<FeedSource>> {
+    let doc = roxmltree::Document::parse(content).context("Failed to parse OPML document")?;
+    let body = doc
+        .descendants()
+        .find(|n| n.has_tag_name("body"))
+        .context("OPML document has no <body> element")?;
+
+    let mut sources = Vec::new();
+    collect_outlines(body, &mut Vec::new(), &mut sources);
+    Ok(sources)
+}
+
+fn collect_outlines(node: roxmltree::Node, folders: &mut Vec<String>, sources: &mut Vec<FeedSource>) {
+    for outline in node.children().filter(|n| n.has_tag_name("outline")) {
+        let title = outline
+            .attribute("title")
+            .or_else(|| outline.attribute("text"))
+            .map(|t| t.trim().to_string())
+            .unwrap_or_default();
+
+        match outline.attribute("xmlUrl").map(str::trim) {
+            Some(url) if !url.is_empty() => {
+                let mut tags: Vec<String> = folders.clone();
+                if let Some(categories) = outline.attribute("category") {
+                    tags.extend(parse_categories(categories));
+                }
+                let mut unique_tags = Vec::new();
+                for tag in tags {
+                    if !unique_tags.contains(&tag) {
+                        unique_tags.push(tag);
+                    }
+                }
+
+                sources.push(FeedSource {
+                    name: if title.is_empty() {
+                        url.to_string()
+                    } else {
+                        title
+                    },
+                    url: url.to_string(),
+                    tags: unique_tags,
+                    id: None,
+                    max_items: None,
+                    max_age_days: None,
+                });
+            }
+            _ => {
+                let is_folder = !title.is_empty();
+                if is_folder {
+                    folders.push(title);
+                }
+                collect_outlines(outline, folders, sources);
+                if is_folder {
+                    folders.pop();
+                }
+            }
+        }
+    }
+}
+
+// The OPML 2.0 `category` attribute is a comma-separated list of
+// slash-delimited paths, e.g. "/tech/rust,news".
+fn parse_categories(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .flat_map(|category| category.split('/'))
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+pub fn render(sources: &[FeedSource]) -> String {
+    let mut folders: IndexMap<&str, Vec<&FeedSource>> = IndexMap::new();
+    let mut untagged = Vec::new();
+    for source in sources {
+        match source.tags.first() {
+            Some(tag) => folders.entry(tag.as_str()).or_default().push(source),
+            None => untagged.push(source),
+        }
+    }
+
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<opml version=\"2.0\">\n");
+    out.push_str("  <head>\n");
+    out.push_str("    <title>miam feeds</title>\n");
+    out.push_str(&format!(
+        "    <dateCreated>{}</dateCreated>\n",
+        chrono::Utc::now().to_rfc2822()
+    ));
+    out.push_str("  </head>\n");
+    out.push_str("  <body>\n");
+
+    for (tag, feeds) in &folders {
+        out.push_str(&format!(
+            "    <outline text=\"{0}\" title=\"{0}\">\n",
+            escape(tag)
+        ));
+        for source in feeds {
+            out.push_str(&format!("      {}\n", render_feed_outline(source)));
+        }
+        out.push_str("    </outline>\n");
+    }
+
+    for source in untagged {
+        out.push_str(&format!("    {}\n", render_feed_outline(source)));
+    }
+
+    out.push_str("  </body>\n");
+    out.push_str("</opml>\n");
+    out
+}
+
+fn render_feed_outline(source: &FeedSource) -> String {
+    let mut outline = format!(
+        "<outline type=\"rss\" text=\"{0}\" title=\"{0}\" xmlUrl=\"{1}\"",
+        escape(&source.name),
+        escape(&source.url)
+    );
+    if !source.tags.is_empty() {
+        outline.push_str(&format!(" category=\"{}\"", escape(&source.tags.join(","))));
+    }
+    outline.push_str("/>");
+    outline
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Appends `imported` to `sources`, skipping feeds whose URL is already present
+/// and renaming feeds whose name would collide with an existing one.
+/// Returns the number of feeds added.
+pub fn merge_sources(sources: &mut Vec<FeedSource>, imported: Vec<FeedSource>) -> usize {
+    let mut added = 0;
+    for mut source in imported {
+        let key = url_key(&source.url);
+        if sources.iter().any(|s| url_key(&s.url) == key) {
+            continue;
+        }
+
+        let base_name = source.name.clone();
+        let mut suffix = 2;
+        while sources.iter().any(|s| s.name == source.name) {
+            source.name = format!("{} ({})", base_name, suffix);
+            suffix += 1;
+        }
+
+        sources.push(source);
+        added += 1;
+    }
+    added
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(name: &str, url: &str, tags: &[&str]) -> FeedSource {
+        FeedSource {
+            name: name.to_string(),
+            url: url.to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            id: None,
+            max_items: None,
+            max_age_days: None,
+        }
+    }
+
+    fn summary(sources: &[FeedSource]) -> Vec<(&str, &str, Vec<&str>)> {
+        sources
+            .iter()
+            .map(|s| {
+                let tags = s.tags.iter().map(String::as_str).collect();
+                (s.name.as_str(), s.url.as_str(), tags)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn nested_outlines_become_tags() {
+        let content = r#"<?xml version="1.0"?>
+            <opml version="2.0"><head><title>Subscriptions</title></head><body>
+              <outline text="Tech">
+                <outline text="Rust" title="Rust">
+                  <outline text="This Week" xmlUrl=" https://this-week-in-rust.org/rss.xml " category="/news/weekly,rust"/>
+                </outline>
+                <outline xmlUrl="https://example.com/untitled.xml"/>
+              </outline>
+              <outline>
+                <outline text="Loose" xmlUrl="https://example.com/loose.xml"/>
+              </outline>
+              <outline text="Empty folder"/>
+            </body></opml>"#;
+        let sources = parse(content).unwrap();
+        assert_eq!(
+            summary(&sources),
+            [
+                (
+                    "This Week",
+                    "https://this-week-in-rust.org/rss.xml",
+                    vec!["Tech", "Rust", "news", "weekly", "rust"],
+                ),
+                (
+                    "https://example.com/untitled.xml",
+                    "https://example.com/untitled.xml",
+                    vec!["Tech"],
+                ),
+                ("Loose", "https://example.com/loose.xml", vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_body_or_bad_xml_is_an_error() {
+        assert!(parse("<opml><head/></opml>").is_err());
+        assert!(parse("<opml><body>").is_err());
+    }
+
+    #[test]
+    fn render_then_parse_round_trips_escaped_values() {
+        let sources = vec![
+            source(
+                "Tom & Jerry's <\"Cartoons\">",
+                "https://example.com/feed?a=1&b=2",
+                &["Fun & Games", "tv"],
+            ),
+            source("Plain", "https://example.com/plain.xml", &["Fun & Games"]),
+            source("Untagged", "https://example.com/untagged.xml", &[]),
+        ];
+        let rendered = render(&sources);
+        assert!(rendered.contains("xmlUrl=\"https://example.com/feed?a=1&amp;b=2\""));
+        assert_eq!(summary(&parse(&rendered).unwrap()), summary(&sources));
+    }
+
+    #[test]
+    fn merge_skips_known_urls_and_renames_clashing_names() {
+        let mut sources = vec![
+            source("Blog", "https://Example.com/feed/", &[]),
+            source("News", "https://news.example/rss", &[]),
+        ];
+        let imported = vec![
+            source("Same blog", "https://example.com/feed", &[]),
+            source("Blog", "https://other.example/feed", &[]),
+            source("Blog", "https://third.example/feed", &[]),
+            source("Different path", "https://example.com/Feed", &[]),
+        ];
+        assert_eq!(merge_sources(&mut sources, imported), 3);
+        let names: Vec<&str> = sources.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["Blog", "News", "Blog (2)", "Blog (3)", "Different path"]);
+    }
+}
@@ -0,0 +1,282 @@
+use crate::feed::FeedItem;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+/// A parsed item filter such as `tag:tech is:unread -"sponsored post"`.
+/// All terms must match; a leading `-` negates a term.
+pub struct Query {
+    terms: Vec<(bool, Term)>,
+}
+
+enum Term {
+    Text(String),
+    Feed(String),
+    Tag(String),
+    Is(Flag),
+    After(DateTime<Utc>),
+    Before(DateTime<Utc>),
+}
+
+enum Flag {
+    Unread,
+    Read,
+    Liked,
+    Video,
+    Article,
+    Short,
+}
+
+/// What the query needs to know about an item beyond the item itself.
+pub struct ItemContext<'a> {
+    pub tags: &'a [String],
+    pub is_video: bool,
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Self {
+        let terms = tokenize(input)
+            .into_iter()
+            .map(|(token, quoted)| {
+                let (negated, token) = match token.strip_prefix('-') {
+                    Some(rest) if !rest.is_empty() && !quoted => (true, rest.to_string()),
+                    _ => (false, token),
+                };
+                (negated, parse_term(&token, quoted))
+            })
+            .collect();
+        Self { terms }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    pub fn matches(&self, item: &FeedItem, context: &ItemContext) -> bool {
+        self.terms
+            .iter()
+            .all(|(negated, term)| term.matches(item, context) != *negated)
+    }
+}
+
+impl Term {
+    fn matches(&self, item: &FeedItem, context: &ItemContext) -> bool {
+        match self {
+            Term::Text(text) => matches_text(item, text),
+            Term::Feed(name) => item.source_name.to_lowercase() == *name,
+            Term::Tag(tag) => context.tags.iter().any(|t| t.to_lowercase() == *tag),
+            Term::Is(Flag::Unread) => !item.seen,
+            Term::Is(Flag::Read) => item.seen,
+            Term::Is(Flag::Liked) => item.liked,
+            Term::Is(Flag::Video) => context.is_video,
+            Term::Is(Flag::Article) => !context.is_video,
+            Term::Is(Flag::Short) => item.is_youtube_short,
+            Term::After(date) => item.date >= *date,
+            Term::Before(date) => item.date < *date,
+        }
+    }
+}
+
+fn matches_text(item: &FeedItem, text: &str) -> bool {
+    item.title.to_lowercase().contains(text)
+        || item
+            .summary
+            .as_ref()
+            .is_some_and(|s| s.to_lowercase().contains(text))
+        || item
+            .author
+            .as_ref()
+            .is_some_and(|a| a.to_lowercase().contains(text))
+        || item
+            .categories
+            .iter()
+            .any(|c| c.to_lowercase().contains(text))
+}
+
+fn parse_term(token: &str, quoted: bool) -> Term {
+    let lower = token.to_lowercase();
+    if quoted {
+        return Term::Text(lower);
+    }
+    let Some((key, value)) = lower.split_once(':') else {
+        return Term::Text(lower);
+    };
+
+    let term = match key {
+        "feed" => Some(Term::Feed(value.to_string())),
+        "tag" => Some(Term::Tag(value.to_string())),
+        "is" => parse_flag(value).map(Term::Is),
+        "after" => parse_date(value).map(Term::After),
+        "before" => parse_date(value).map(Term::Before),
+        _ => None,
+    };
+    // Anything that isn't a known filter is searched for as text, e.g. "re:"
+    term.unwrap_or(Term::Text(lower))
+}
+
+fn parse_flag(value: &str) -> Option<Flag> {
+    match value {
+        "unread" | "new" => Some(Flag::Unread),
+        "read" | "seen" => Some(Flag::Read),
+        "liked" => Some(Flag::Liked),
+        "video" => Some(Flag::Video),
+        "article" => Some(Flag::Article),
+        "short" => Some(Flag::Short),
+        _ => None,
+    }
+}
+
+// Accepts an absolute date or an age relative to now, so `after:7d` keeps
+// the last week and `before:2w` keeps anything older than two weeks.
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    if let Some(age) = parse_age(value) {
+        // Ages reaching past the dates chrono can hold aren't valid either
+        return Utc::now().checked_sub_signed(age);
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()?
+        .and_hms_opt(0, 0, 0)
+        .map(|d| d.and_utc())
+}
+
+fn parse_age(value: &str) -> Option<Duration> {
+    let unit = value.chars().last()?;
+    let count: i64 = value[..value.len() - unit.len_utf8()].parse().ok()?;
+    match unit {
+        'h' => Duration::try_hours(count),
+        'd' => Duration::try_days(count),
+        'w' => Duration::try_weeks(count),
+        _ => None,
+    }
+}
+
+// Splits on whitespace outside double quotes and strips the quotes, so
+// `feed:"Rust Blog"` is one token. The flag tells whether the token started
+// with a quote, which makes it a plain phrase.
+fn tokenize(input: &str) -> Vec<(String, bool)> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut quoted = false;
+    let mut started = false;
+
+    for c in input.chars() {
+        match c {
+            '"' => {
+                if !started {
+                    quoted = true;
+                }
+                in_quotes = !in_quotes;
+                started = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push((std::mem::take(&mut current), quoted));
+                }
+                quoted = false;
+                started = false;
+            }
+            c => {
+                current.push(c);
+                started = true;
+            }
+        }
+    }
+    if !current.is_empty() {
+        tokens.push((current, quoted));
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(title: &str, days_old: i64) -> FeedItem {
+        FeedItem {
+            title: title.to_string(),
+            link: None,
+            date: Utc::now() - Duration::days(days_old),
+            source_name: "Rust Blog".to_string(),
+            is_youtube_short: false,
+            summary: Some("A sponsored post about crates".to_string()),
+            content: None,
+            author: Some("Ferris".to_string()),
+            categories: vec!["Release".to_string()],
+            guid: None,
+            seen: false,
+            liked: false,
+            snippet: None,
+        }
+    }
+
+    fn matches(query: &str, item: &FeedItem) -> bool {
+        let tags = ["Tech".to_string()];
+        let context = ItemContext {
+            tags: &tags,
+            is_video: false,
+        };
+        Query::parse(query).matches(item, &context)
+    }
+
+    #[test]
+    fn quotes_group_words_and_mark_phrases() {
+        assert_eq!(
+            tokenize(r#"feed:"Rust Blog"  "is:unread" -"sponsored post" plain"#),
+            [
+                ("feed:Rust Blog".to_string(), false),
+                ("is:unread".to_string(), true),
+                ("-sponsored post".to_string(), false),
+                ("plain".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn filters_match_item_fields_ignoring_case() {
+        let item = item("Announcing Rust 1.80", 1);
+        assert!(matches(r#"feed:"rust blog" tag:tech is:unread"#, &item));
+        assert!(matches("ferris release CRATES", &item));
+        assert!(!matches("is:read", &item));
+        assert!(!matches("tag:news", &item));
+    }
+
+    #[test]
+    fn leading_dash_negates_a_term() {
+        let item = item("Announcing Rust 1.80", 1);
+        assert!(!matches(r#"-"sponsored post""#, &item));
+        assert!(!matches("-is:unread", &item));
+        assert!(matches("-tag:news", &item));
+        // A lone dash or a quoted one is text to look for
+        assert!(!matches("-", &item));
+        assert!(!matches(r#""-rust""#, &item));
+    }
+
+    #[test]
+    fn quoted_or_unknown_filters_are_text() {
+        let item = item("re: is:unread", 1);
+        assert!(matches(r#""is:unread""#, &item));
+        assert!(matches("re:", &item));
+        assert!(!matches(r#""tag:tech""#, &item));
+        assert!(!matches("is:nothing", &item));
+    }
+
+    #[test]
+    fn after_and_before_take_ages_and_dates() {
+        let recent = item("recent", 2);
+        let old = item("old", 30);
+        assert!(matches("after:7d", &recent));
+        assert!(!matches("after:7d", &old));
+        assert!(matches("before:2w", &old));
+        assert!(!matches("before:2w", &recent));
+        assert!(matches("after:1w before:24h", &recent));
+        assert!(matches("after:2000-01-01", &old));
+        assert!(!matches("before:2000-01-01", &old));
+    }
+
+    #[test]
+    fn ages_past_the_date_range_are_text() {
+        assert!(parse_date("100000000d").is_none());
+        assert!(parse_date("99999999999999w").is_none());
+        assert!(!matches("after:100000000d", &item("anything", 1)));
+        assert!(matches("after:100000000d", &item("after:100000000d", 1)));
+    }
+}
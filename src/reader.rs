@@ -1,12 +1,48 @@
+use crate::document::{self, Block};
+use crate::feed::FeedItem;
 use anyhow::Result;
+use image::imageops::FilterType;
+use image::{Rgb, RgbImage};
 use readability::extractor;
 use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::io::Cursor;
 use url::Url;
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Article {
     pub title: String,
+    /// Plain text of the article, used for search.
     pub content: String,
+    #[serde(default)]
+    pub blocks: Vec<Block>,
+}
+
+impl Article {
+    fn from_html(title: String, html: &str, base: Option<&Url>) -> Self {
+        let blocks = document::parse(html, base);
+        Self {
+            title,
+            content: document::to_text(&blocks),
+            blocks,
+        }
+    }
+
+    /// The blocks to show in the reader. Articles cached before blocks were
+    /// stored only have their text.
+    pub fn document(&self) -> Cow<'_, [Block]> {
+        if self.blocks.is_empty() {
+            Cow::Owned(document::from_text(&self.content))
+        } else {
+            Cow::Borrowed(&self.blocks)
+        }
+    }
+
+    /// The article's link targets, numbered as the reader shows them.
+    pub fn links(&self) -> Vec<String> {
+        document::links(&self.document())
+    }
 }
 
 fn create_client(user_agent: Option<&str>, timeout_secs: u64) -> Result<Client> {
@@ -18,6 +54,12 @@ fn create_client(user_agent: Option<&str>, timeout_secs: u64) -> Result<Client>
         .map_err(|e| anyhow::anyhow!("Failed to create HTTP client: {}", e))
 }
 
+pub fn article_from_feed_item(item: &FeedItem) -> Option<Article> {
+    let html = item.content.as_deref().or(item.summary.as_deref())?;
+    let base = item.link.as_deref().and_then(|link| Url::parse(link).ok());
+    Some(Article::from_html(item.title.clone(), html, base.as_ref()))
+}
+
 pub fn fetch_article(url_str: &str, paywall_remover: bool) -> Result<Article> {
     if paywall_remover {
         fetch_with_paywall_bypass(url_str)
@@ -68,81 +110,38 @@ fn fetch_direct(url_str: &str, user_agent: Option<&str>, timeout_secs: u64) -> R
     let html = response.text()?;
 
     let url = Url::parse(url_str)?;
-    let mut cursor = Cursor::new(html);
+    let mut cursor = Cursor::new(html.as_bytes());
     let product = extractor::extract(&mut cursor, &url)
         .map_err(|e| anyhow::anyhow!("Failed to parse article: {}", e))?;
 
-    let content = html_to_text(&product.content);
-
-    Ok(Article {
-        title: product.title,
-        content,
-    })
+    let mut article = Article::from_html(product.title, &product.content, Some(&url));
+    document::restore_code_languages(&mut article.blocks, &html);
+    Ok(article)
 }
 
-fn html_to_text(html: &str) -> String {
-    // Simple string-based approach for better reliability
-    let mut text = html.to_string();
-
-    // Remove script tags with content (simple string search)
-    while let Some(start) = text.find("<script") {
-        if let Some(end) = text[start..].find("</script>") {
-            text.replace_range(start..start + end + 9, "");
-        } else {
-            break;
-        }
-    }
-
-    // Remove style tags with content
-    while let Some(start) = text.find("<style") {
-        if let Some(end) = text[start..].find("</style>") {
-            text.replace_range(start..start + end + 8, "");
-        } else {
-            break;
-        }
-    }
+const MAX_IMAGE_WIDTH: u32 = 1600;
 
-    // Add spacing for block elements
-    for tag in &["</p>", "</div>", "</h1>", "</h2>", "</h3>", "</h4>", "</h5>", "</h6>", "</li>", "</blockquote>"] {
-        text = text.replace(tag, "\n");
-    }
-
-    // Add bullet for list items
-    text = text.replace("<li>", "\n• ").replace("<li ", "\n• <");
-
-    // Remove all remaining HTML tags (simple character-by-character)
-    let mut result = String::with_capacity(text.len());
-    let mut in_tag = false;
-    for c in text.chars() {
-        match c {
-            '<' => in_tag = true,
-            '>' => in_tag = false,
-            _ if !in_tag => result.push(c),
-            _ => {}
-        }
+/// Downloads and decodes an image. Transparency is flattened onto white, as
+/// diagrams are drawn for light pages, and very large images are scaled down.
+pub fn fetch_image(url: &str) -> Result<RgbImage> {
+    let client = create_client(None, 15)?;
+    let response = client.get(url).send()?;
+    let status = response.status();
+    if !status.is_success() {
+        anyhow::bail!("HTTP {}", status.as_u16());
     }
-
-    // Decode common HTML entities
-    result = result
-        .replace("&nbsp;", " ")
-        .replace("&amp;", "&")
-        .replace("&lt;", "<")
-        .replace("&gt;", ">")
-        .replace("&quot;", "\"")
-        .replace("&apos;", "'")
-        .replace("&#39;", "'")
-        .replace("&mdash;", "\u{2014}")
-        .replace("&ndash;", "\u{2013}")
-        .replace("&hellip;", "\u{2026}")
-        .replace("&ldquo;", "\u{201C}")
-        .replace("&rdquo;", "\u{201D}")
-        .replace("&lsquo;", "\u{2018}")
-        .replace("&rsquo;", "\u{2019}");
-
-    // Collapse multiple newlines
-    while result.contains("\n\n\n") {
-        result = result.replace("\n\n\n", "\n\n");
+    let mut image = image::load_from_memory(&response.bytes()?)?;
+    if image.width() > MAX_IMAGE_WIDTH {
+        image = image.resize(MAX_IMAGE_WIDTH, u32::MAX, FilterType::Triangle);
     }
+    let image = image.to_rgba8();
+    Ok(RgbImage::from_fn(image.width(), image.height(), |x, y| {
+        let pixel = image.get_pixel(x, y);
+        let alpha = u32::from(pixel[3]);
+        Rgb([0, 1, 2].map(|i| ((u32::from(pixel[i]) * alpha + 255 * (255 - alpha)) / 255) as u8))
+    }))
+}
 
-    result.trim().to_string()
+pub fn html_to_text(html: &str) -> String {
+    document::to_text(&document::parse(html, None))
 }
@@ -1,51 +1,57 @@
+use crate::db;
 use anyhow::Result;
-use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
-use std::fs;
-use std::path::PathBuf;
 
-#[derive(Clone, Serialize, Deserialize, Default)]
+#[derive(Clone, Default)]
 pub struct SeenStatus {
     seen_items: HashSet<String>,
+    pending: Vec<(String, bool)>,
 }
 
 impl SeenStatus {
     pub fn load() -> Result<Self> {
-        let path = Self::get_path()?;
+        let conn = db::open()?;
+        Ok(Self {
+            seen_items: db::load_set(&conn, "seen")?,
+            pending: Vec::new(),
+        })
+    }
 
-        if !path.exists() {
-            return Ok(Self::default());
+    pub fn save(&mut self) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
         }
-
-        let content = fs::read_to_string(&path)?;
-        let status: SeenStatus = serde_yaml::from_str(&content)?;
-        Ok(status)
+        let mut conn = db::open()?;
+        db::update_set(&mut conn, "seen", &self.pending)?;
+        self.pending.clear();
+        Ok(())
     }
 
-    pub fn save(&self) -> Result<()> {
-        let path = Self::get_path()?;
-
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent)?;
+    pub fn mark_seen(&mut self, identifier: &str) {
+        if self.seen_items.insert(identifier.to_string()) {
+            self.pending.push((identifier.to_string(), true));
         }
+    }
 
-        let yaml = serde_yaml::to_string(&self)?;
-        fs::write(&path, yaml)?;
-        Ok(())
+    pub fn mark_unseen(&mut self, identifier: &str) {
+        if self.seen_items.remove(identifier) {
+            self.pending.push((identifier.to_string(), false));
+        }
     }
 
-    pub fn mark_seen(&mut self, identifier: &str) {
-        self.seen_items.insert(identifier.to_string());
+    pub fn toggle_seen(&mut self, identifier: &str) -> bool {
+        if self.is_seen(identifier) {
+            self.mark_unseen(identifier);
+            false
+        } else {
+            self.mark_seen(identifier);
+            true
+        }
     }
 
     pub fn is_seen(&self, identifier: &str) -> bool {
         self.seen_items.contains(identifier)
     }
-
-    fn get_path() -> Result<PathBuf> {
-        let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
-        Ok(home.join(".miam").join("seen.yml"))
-    }
 }
 
 pub fn get_item_identifier(link: &Option<String>, title: &str) -> String {
@@ -0,0 +1,53 @@
+use indexmap::IndexMap;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SmartFeedKind {
+    All,
+    Liked,
+    Articles,
+    Videos,
+    Saved,
+}
+
+/// A sidebar entry showing the items of every feed that match a query.
+#[derive(Clone)]
+pub struct SmartFeed {
+    pub name: String,
+    pub query: String,
+    pub kind: SmartFeedKind,
+}
+
+impl SmartFeed {
+    fn new(name: &str, query: &str, kind: SmartFeedKind) -> Self {
+        Self {
+            name: name.to_string(),
+            query: query.to_string(),
+            kind,
+        }
+    }
+}
+
+/// The built-in entries followed by the user's saved searches, in the
+/// order they appear in the config.
+pub fn smart_feeds(saved_searches: &IndexMap<String, String>) -> Vec<SmartFeed> {
+    let mut feeds = vec![
+        SmartFeed::new("All", "", SmartFeedKind::All),
+        SmartFeed::new("Liked", "is:liked", SmartFeedKind::Liked),
+        SmartFeed::new("Articles", "is:article", SmartFeedKind::Articles),
+        SmartFeed::new("Videos", "is:video", SmartFeedKind::Videos),
+    ];
+    feeds.extend(
+        saved_searches
+            .iter()
+            .map(|(name, query)| SmartFeed::new(name, query, SmartFeedKind::Saved)),
+    );
+    feeds
+}
+
+pub fn saved_searches(feeds: &[SmartFeed]) -> IndexMap<String, String> {
+    feeds
+        .iter()
+        .filter(|f| f.kind == SmartFeedKind::Saved)
+        .map(|f| (f.name.clone(), f.query.clone()))
+        .collect()
+}
@@ -0,0 +1,131 @@
+//! A small HTTP server standing in for feed hosts in tests.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Once};
+use std::thread;
+use std::time::Duration;
+
+pub struct Request {
+    pub path: String,
+    /// Header names are lowercase.
+    pub headers: HashMap<String, String>,
+}
+
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: String,
+    pub delay: Duration,
+}
+
+impl Response {
+    pub fn ok(content_type: &str, body: &str) -> Self {
+        Self {
+            status: 200,
+            headers: vec![("Content-Type", content_type.to_string())],
+            body: body.to_string(),
+            delay: Duration::ZERO,
+        }
+    }
+
+    pub fn status(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: String::new(),
+            delay: Duration::ZERO,
+        }
+    }
+
+    pub fn header(mut self, name: &'static str, value: &str) -> Self {
+        self.headers.push((name, value.to_string()));
+        self
+    }
+
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+/// Serves each request with `handler` on its own thread until the test ends.
+/// Returns the server's base URL, without a trailing slash.
+pub fn serve<F>(handler: F) -> String
+where
+    F: Fn(&Request) -> Response + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let handler = Arc::new(handler);
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let handler = Arc::clone(&handler);
+            thread::spawn(move || answer(stream, &*handler));
+        }
+    });
+    base
+}
+
+fn answer(mut stream: TcpStream, handler: &dyn Fn(&Request) -> Response) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/").to_string();
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).unwrap_or(0) == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let response = handler(&Request { path, headers });
+    thread::sleep(response.delay);
+    let mut out = format!("HTTP/1.1 {} Test\r\n", response.status);
+    for (name, value) in &response.headers {
+        out.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    out.push_str(&format!(
+        "Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.body.len(),
+        response.body
+    ));
+    let _ = stream.write_all(out.as_bytes());
+}
+
+/// Points the home directory at a fresh temporary one, so tests that store
+/// feeds never touch the real `~/.miam`. Every test in the run shares it.
+pub fn isolate_home() {
+    static HOME: Once = Once::new();
+    HOME.call_once(|| {
+        let home: PathBuf = std::env::temp_dir().join(format!("miam-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&home);
+        std::fs::create_dir_all(&home).unwrap();
+        std::env::set_var("HOME", &home);
+    });
+}
+
+pub fn rss(title: &str, items: &[&str]) -> String {
+    let items: String = items
+        .iter()
+        .map(|item| {
+            format!(
+                "<item><title>{0}</title><link>http://example.com/{0}</link>\
+                 <pubDate>Mon, 01 Jan 2024 00:00:00 GMT</pubDate></item>",
+                item
+            )
+        })
+        .collect();
+    format!(
+        "<?xml version=\"1.0\"?><rss version=\"2.0\"><channel><title>{}</title>{}</channel></rss>",
+        title, items
+    )
+}
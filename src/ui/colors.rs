@@ -5,3 +5,5 @@ pub const SECONDARY: Color = Color::Rgb(187, 134, 252);
 pub const DIM: Color = Color::Rgb(117, 117, 117);
 pub const SUCCESS: Color = Color::Rgb(129, 199, 132);
 pub const SELECTED_BG: Color = Color::Rgb(60, 60, 70);
+pub const WARNING: Color = Color::Rgb(255, 183, 77);
+pub const CODE: Color = Color::Rgb(206, 145, 120);
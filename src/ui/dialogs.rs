@@ -1,11 +1,11 @@
-use crate::app::{App, Focus};
-use crate::ui::colors::{PRIMARY, SECONDARY, SUCCESS};
-use crate::ui::utils::centered_rect;
+use crate::app::{App, EditField, Focus};
+use crate::ui::colors::{DIM, PRIMARY, SECONDARY, SELECTED_BG, SUCCESS, WARNING};
+use crate::ui::utils::{centered_rect, time_ago, truncate};
 use ratatui::{
     layout::{Constraint, Direction, Layout},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Clear, Paragraph},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
     Frame,
 };
 
@@ -52,6 +52,42 @@ pub fn render_filter_dialog(f: &mut Frame, app: &App) {
     f.set_cursor_position((area.x + app.filter.len() as u16 + 1, area.y + 1));
 }
 
+pub fn render_search_dialog(f: &mut Frame, app: &App) {
+    let area = centered_rect(60, 3, f.area());
+
+    f.render_widget(Clear, area);
+
+    let search = Paragraph::new(app.search_input.as_str())
+        .style(Style::default().fg(Color::White))
+        .block(
+            Block::default()
+                .title(Span::styled(" \u{f002} Search All Feeds ", Style::default().fg(SECONDARY)))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(SECONDARY)),
+        );
+    f.render_widget(search, area);
+
+    f.set_cursor_position((area.x + app.search_input.chars().count() as u16 + 1, area.y + 1));
+}
+
+pub fn render_mark_older_dialog(f: &mut Frame, app: &App) {
+    let area = centered_rect(60, 3, f.area());
+
+    f.render_widget(Clear, area);
+
+    let input = Paragraph::new(app.mark_older_input.as_str())
+        .style(Style::default().fg(Color::White))
+        .block(
+            Block::default()
+                .title(Span::styled(" \u{f00c} Mark Read Older Than (days) ", Style::default().fg(PRIMARY)))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(PRIMARY)),
+        );
+    f.render_widget(input, area);
+
+    f.set_cursor_position((area.x + app.mark_older_input.len() as u16 + 1, area.y + 1));
+}
+
 pub fn render_tag_editor(f: &mut Frame, app: &App) {
     let tag_count = app.editing_tags.len() as u16;
     let tags_height = if tag_count > 0 {
@@ -141,3 +177,150 @@ pub fn render_tag_editor(f: &mut Frame, app: &App) {
 
     f.set_cursor_position((chunks[0].x + app.tag_input.len() as u16 + 1, chunks[0].y + 1));
 }
+
+pub fn render_edit_feed_dialog(f: &mut Frame, app: &App) {
+    let area = centered_rect(70, 6, f.area());
+
+    f.render_widget(Clear, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Length(3)])
+        .split(area);
+
+    let fields = [
+        (EditField::Name, " \u{f040} Name ", app.edit_name.as_str()),
+        (EditField::Url, " \u{f0c1} URL ", app.edit_url.as_str()),
+    ];
+    for (chunk, (field, title, value)) in chunks.iter().zip(fields) {
+        let color = if app.edit_field == field { PRIMARY } else { DIM };
+        let input = Paragraph::new(value)
+            .style(Style::default().fg(Color::White))
+            .block(
+                Block::default()
+                    .title(Span::styled(title, Style::default().fg(color)))
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(color)),
+            );
+        f.render_widget(input, *chunk);
+    }
+
+    let (chunk, value) = match app.edit_field {
+        EditField::Name => (chunks[0], &app.edit_name),
+        EditField::Url => (chunks[1], &app.edit_url),
+    };
+    f.set_cursor_position((chunk.x + value.chars().count() as u16 + 1, chunk.y + 1));
+}
+
+pub fn render_feed_picker(f: &mut Frame, app: &App) {
+    let dialog_width = (f.area().width * 80 / 100).max(40);
+    let dialog_height = (app.discovered_feeds.len() as u16 * 2 + 2).min(f.area().height.saturating_sub(4));
+    let area = centered_rect(dialog_width, dialog_height, f.area());
+    let text_width = dialog_width.saturating_sub(6) as usize;
+
+    f.render_widget(Clear, area);
+
+    let items: Vec<ListItem> = app
+        .discovered_feeds
+        .iter()
+        .enumerate()
+        .map(|(idx, feed)| {
+            let style = if idx == app.discovered_feed_index {
+                Style::default().fg(Color::White).bg(SELECTED_BG)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            let title = feed.title.clone().unwrap_or_else(|| "Untitled feed".to_string());
+            ListItem::new(vec![
+                Line::from(format!("  {}", truncate(&title, text_width))),
+                Line::from(Span::styled(
+                    format!("    {}", truncate(&feed.url, text_width.saturating_sub(2))),
+                    Style::default().fg(DIM),
+                )),
+            ])
+            .style(style)
+        })
+        .collect();
+
+    let mut list_state = ListState::default();
+    list_state.select(Some(app.discovered_feed_index));
+
+    let picker = List::new(items).block(
+        Block::default()
+            .title(Span::styled(" \u{f09e} Pick a Feed ", Style::default().fg(PRIMARY)))
+            .title_bottom(Span::styled(" Enter add • Esc cancel ", Style::default().fg(DIM)))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(PRIMARY)),
+    );
+    f.render_stateful_widget(picker, area, &mut list_state);
+}
+
+pub fn render_health_dialog(f: &mut Frame, app: &App) {
+    let failing = app.get_failing_feeds();
+    let dialog_width = (f.area().width * 80 / 100).max(40);
+    let text_width = dialog_width.saturating_sub(4) as usize;
+
+    let mut lines = Vec::new();
+    if failing.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "All feeds refreshed successfully.",
+            Style::default().fg(SUCCESS),
+        )));
+    }
+
+    for (source, health) in &failing {
+        let mut header = vec![
+            Span::styled("⚠ ", Style::default().fg(WARNING)),
+            Span::styled(
+                source.name.clone(),
+                Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(
+                format!(
+                    "  {} failure{}",
+                    health.consecutive_failures,
+                    if health.consecutive_failures == 1 { "" } else { "s" }
+                ),
+                Style::default().fg(WARNING),
+            ),
+        ];
+        if let Some(status) = health.last_status {
+            header.push(Span::styled(format!(" • HTTP {}", status), Style::default().fg(WARNING)));
+        }
+        lines.push(Line::from(header));
+
+        let error = health.last_error.as_deref().unwrap_or("Unknown error");
+        lines.push(Line::from(Span::styled(
+            format!("  {}", truncate(error, text_width.saturating_sub(2))),
+            Style::default().fg(Color::White),
+        )));
+
+        let last_success = health
+            .last_success
+            .as_ref()
+            .map(time_ago)
+            .unwrap_or_else(|| "never".to_string());
+        lines.push(Line::from(Span::styled(
+            format!("  {} • last success: {}", truncate(&source.url, text_width / 2), last_success),
+            Style::default().fg(DIM),
+        )));
+        lines.push(Line::from(""));
+    }
+
+    let dialog_height = (lines.len() as u16 + 2).min(f.area().height.saturating_sub(4)).max(3);
+    let area = centered_rect(dialog_width, dialog_height, f.area());
+
+    f.render_widget(Clear, area);
+
+    let title = format!(" \u{f071} Feed Health ({} failing) ", failing.len());
+    let health = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .block(
+            Block::default()
+                .title(Span::styled(title, Style::default().fg(WARNING)))
+                .title_bottom(Span::styled(" Esc to close ", Style::default().fg(DIM)))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(WARNING)),
+        );
+    f.render_widget(health, area);
+}
@@ -0,0 +1,461 @@
+use crate::config::ImageProtocol;
+use crate::document::{self, Block, Inline};
+use crate::ui::colors::{CODE, DIM, PRIMARY, SECONDARY, SUCCESS};
+use crate::ui::{highlight, image};
+use ::image::RgbImage;
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span},
+};
+use std::collections::HashMap;
+use textwrap::core::display_width;
+
+/// The images the reader can show and how it draws them.
+pub struct Images<'a> {
+    pub loaded: &'a HashMap<String, Option<RgbImage>>,
+    pub protocol: ImageProtocol,
+    /// The pixel size of a terminal cell.
+    pub cell: (u16, u16),
+}
+
+/// An article laid out as lines, with the cells left blank for images the
+/// terminal draws itself.
+#[derive(Default)]
+pub struct Rendered {
+    pub lines: Vec<Line<'static>>,
+    pub images: Vec<ImageSlot>,
+}
+
+/// `height` lines from `line`, `width` columns from column `x`.
+pub struct ImageSlot {
+    pub src: String,
+    pub line: usize,
+    pub x: usize,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Rendered {
+    // Adds `other` below, its lines indented by `x` columns.
+    fn append(&mut self, other: Rendered, x: usize) {
+        let offset = self.lines.len();
+        self.images.extend(other.images.into_iter().map(|slot| ImageSlot {
+            line: slot.line + offset,
+            x: slot.x + x,
+            ..slot
+        }));
+        self.lines.extend(other.lines);
+    }
+}
+
+impl From<Vec<Line<'static>>> for Rendered {
+    fn from(lines: Vec<Line<'static>>) -> Self {
+        Self { lines, images: Vec::new() }
+    }
+}
+
+/// Lays the blocks out as lines of at most `width` columns. Links are
+/// numbered inline and listed at the end. Code isn't wrapped; it is shifted
+/// left by `code_scroll` columns instead.
+pub fn render(blocks: &[Block], width: usize, code_scroll: usize, images: &Images) -> Rendered {
+    let width = width.max(10);
+    let links = document::links(blocks);
+    let renderer = Renderer { links: &links, code_scroll, images };
+    let mut rendered = renderer.render_blocks(blocks, width, true);
+    if !links.is_empty() {
+        rendered.lines.push(Line::from(""));
+        rendered.lines.push(Line::from(Span::styled("─".repeat(width), Style::default().fg(DIM))));
+        rendered.lines.extend(renderer.render_footnotes(width));
+    }
+    rendered
+}
+
+struct Renderer<'a> {
+    links: &'a [String],
+    code_scroll: usize,
+    images: &'a Images<'a>,
+}
+
+impl Renderer<'_> {
+    // `spaced` puts a blank line between blocks; list items are kept tight.
+    fn render_blocks(&self, blocks: &[Block], width: usize, spaced: bool) -> Rendered {
+        let mut rendered = Rendered::default();
+        for block in blocks {
+            if spaced && !rendered.lines.is_empty() {
+                rendered.lines.push(Line::from(""));
+            }
+            rendered.append(self.render_block(block, width), 0);
+        }
+        rendered
+    }
+
+    fn render_block(&self, block: &Block, width: usize) -> Rendered {
+        match block {
+            Block::Heading { level, text } => {
+                let color = match level {
+                    1 => PRIMARY,
+                    2 => SECONDARY,
+                    _ => SUCCESS,
+                };
+                let style = Style::default().fg(color).add_modifier(Modifier::BOLD);
+                self.wrap_inlines(text, width)
+                    .into_iter()
+                    .map(|line| line.patch_style(style))
+                    .collect::<Vec<_>>()
+                    .into()
+            }
+            Block::Paragraph(text) => self.wrap_inlines(text, width).into(),
+            Block::List { ordered, start, items } => self.render_list(*ordered, *start, items, width),
+            Block::Quote(inner) => {
+                let inner = self.render_blocks(inner, width.saturating_sub(2), true);
+                let lines = inner
+                    .lines
+                    .into_iter()
+                    .map(|line| {
+                        let line = line.patch_style(Style::default().fg(DIM).add_modifier(Modifier::ITALIC));
+                        indent(line, Span::styled("▎ ", Style::default().fg(DIM)))
+                    })
+                    .collect();
+                let mut rendered = Rendered::default();
+                rendered.append(Rendered { lines, images: inner.images }, 2);
+                rendered
+            }
+            Block::Code { language, text } => self.render_code(text, language.as_deref(), width).into(),
+            Block::Table { header, rows } => {
+                let rows: Vec<Vec<String>> = rows
+                    .iter()
+                    .map(|row| row.iter().map(|cell| self.cell_text(cell)).collect())
+                    .collect();
+                render_table(*header, &rows, width).into()
+            }
+            Block::Image { src, alt } => self.render_image(src, alt, width),
+            Block::Rule => vec![Line::from(Span::styled("─".repeat(width), Style::default().fg(DIM)))].into(),
+        }
+    }
+
+    fn render_list(&self, ordered: bool, start: usize, items: &[Vec<Block>], width: usize) -> Rendered {
+        let marker_width = if ordered {
+            format!("{}. ", start + items.len().saturating_sub(1)).len()
+        } else {
+            2
+        };
+        let mut rendered = Rendered::default();
+        for (i, item) in items.iter().enumerate() {
+            let marker = if ordered {
+                format!("{:>width$}", format!("{}. ", start + i), width = marker_width)
+            } else {
+                "• ".to_string()
+            };
+            let item = self.render_blocks(item, width.saturating_sub(marker_width), false);
+            let lines = hang(item.lines, marker, marker_width);
+            rendered.append(Rendered { lines, images: item.images }, marker_width);
+        }
+        rendered
+    }
+
+    // Terminals with a graphics protocol get blank lines to draw the image
+    // over; others get it in half blocks. Until the image has loaded, or if
+    // it can't be, its alt text stands in for it.
+    fn render_image(&self, src: &str, alt: &str, width: usize) -> Rendered {
+        let loaded = self.images.loaded.get(src).and_then(Option::as_ref);
+        let Some(loaded) = loaded else {
+            let label = if alt.is_empty() { "[image]".to_string() } else { format!("[image: {}]", alt) };
+            return vec![Line::from(Span::styled(label, Style::default().fg(DIM)))].into();
+        };
+
+        let (columns, rows) = image::size_in_cells(loaded, width, self.images.cell);
+        match self.images.protocol {
+            ImageProtocol::Halfblocks => image::half_blocks(src, loaded, columns, rows).into(),
+            _ => Rendered {
+                lines: vec![Line::from(""); usize::from(rows)],
+                images: vec![ImageSlot {
+                    src: src.to_string(),
+                    line: 0,
+                    x: 0,
+                    width: columns,
+                    height: rows,
+                }],
+            },
+        }
+    }
+
+    // Each block scrolls only as far as its own widest line, so short
+    // blocks stay in view. Arrows mark lines that continue out of sight.
+    fn render_code(&self, text: &str, language: Option<&str>, width: usize) -> Vec<Line<'static>> {
+        let lines = highlight::highlight(text, language);
+        let available = width.saturating_sub(2);
+        let widest = lines.iter().map(Line::width).max().unwrap_or(0);
+        let skip = self.code_scroll.min(widest.saturating_sub(available));
+        let marker = Style::default().fg(DIM);
+
+        lines
+            .into_iter()
+            .map(|line| {
+                let line_width = line.width();
+                let more_right = line_width > skip + available;
+                let take = if more_right { available.saturating_sub(1) } else { available };
+                let mut spans = vec![if skip > 0 && line_width > 0 {
+                    Span::styled("‹ ", marker)
+                } else {
+                    Span::raw("  ")
+                }];
+                spans.extend(crop(line, skip, take));
+                if more_right {
+                    spans.push(Span::styled("›", marker));
+                }
+                Line::from(spans)
+            })
+            .collect()
+    }
+
+    fn render_footnotes(&self, width: usize) -> Vec<Line<'static>> {
+        let marker_width = format!("[{}] ", self.links.len()).len();
+        let mut lines = Vec::new();
+        for (i, link) in self.links.iter().enumerate() {
+            let marker = format!("{:<width$}", format!("[{}]", i + 1), width = marker_width);
+            let text = [Inline { text: link.clone(), ..Inline::default() }];
+            let rendered = self
+                .wrap_inlines(&text, width.saturating_sub(marker_width))
+                .into_iter()
+                .map(|line| line.patch_style(Style::default().fg(DIM)))
+                .collect();
+            lines.extend(hang(rendered, marker, marker_width));
+        }
+        lines
+    }
+
+    // Splits the text into words. Text that isn't separated by a space, like
+    // a link and its `[n]` marker, stays one word even across styles.
+    fn tokenize(&self, inlines: &[Inline]) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        let mut pending_space = false;
+        for (k, inline) in inlines.iter().enumerate() {
+            let style = inline_style(inline);
+            for (i, segment) in inline.text.split('\n').enumerate() {
+                if i > 0 {
+                    tokens.push(Token::Break);
+                    pending_space = false;
+                }
+                for (j, word) in segment.split(' ').enumerate() {
+                    if j > 0 {
+                        pending_space = true;
+                    }
+                    if !word.is_empty() {
+                        push_word(&mut tokens, Span::styled(word.to_string(), style), pending_space);
+                        pending_space = false;
+                    }
+                }
+            }
+            // Only the last run of a link gets a marker, so a link with bold
+            // words inside it is numbered once
+            let link_continues = inlines.get(k + 1).is_some_and(|next| next.link == inline.link);
+            if let Some(number) = self.link_number(inline).filter(|_| !link_continues) {
+                // The marker sticks to the link even when its text ends in a space
+                let marker = Span::styled(format!("[{}]", number), Style::default().fg(PRIMARY));
+                push_word(&mut tokens, marker, false);
+            }
+        }
+        tokens
+    }
+
+    // Table cells are plain text, with the same `[n]` after each link.
+    fn cell_text(&self, cell: &[Inline]) -> String {
+        let mut text = String::new();
+        for (i, inline) in cell.iter().enumerate() {
+            text.push_str(&inline.text);
+            let ends_link = cell.get(i + 1).is_none_or(|next| next.link != inline.link);
+            if let Some(number) = self.link_number(inline).filter(|_| ends_link) {
+                text.push_str(&format!("[{}]", number));
+            }
+        }
+        text
+    }
+
+    fn link_number(&self, inline: &Inline) -> Option<usize> {
+        let link = inline.link.as_ref()?;
+        self.links.iter().position(|l| l == link).map(|i| i + 1)
+    }
+
+    /// Word-wraps styled text. Words wider than the line are split.
+    fn wrap_inlines(&self, inlines: &[Inline], width: usize) -> Vec<Line<'static>> {
+        let mut lines = Vec::new();
+        let mut spans: Vec<Span<'static>> = Vec::new();
+        let mut line_width = 0;
+
+        for token in self.tokenize(inlines) {
+            let (word, space_before) = match token {
+                Token::Break => {
+                    lines.push(Line::from(std::mem::take(&mut spans)));
+                    line_width = 0;
+                    continue;
+                }
+                Token::Word { spans, space_before } => (spans, space_before),
+            };
+            let word_width: usize = word.iter().map(|span| display_width(&span.content)).sum();
+            let space = usize::from(space_before && line_width > 0);
+            if line_width > 0 && line_width + space + word_width > width {
+                lines.push(Line::from(std::mem::take(&mut spans)));
+                line_width = 0;
+            } else if space == 1 {
+                spans.push(Span::raw(" "));
+                line_width += 1;
+            }
+
+            if word_width <= width {
+                spans.extend(word);
+                line_width += word_width;
+                continue;
+            }
+            for span in word {
+                for c in span.content.chars() {
+                    let char_width = display_width(c.encode_utf8(&mut [0; 4]));
+                    if line_width + char_width > width {
+                        lines.push(Line::from(std::mem::take(&mut spans)));
+                        line_width = 0;
+                    }
+                    spans.push(Span::styled(c.to_string(), span.style));
+                    line_width += char_width;
+                }
+            }
+        }
+        if !spans.is_empty() {
+            lines.push(Line::from(spans));
+        }
+        lines
+    }
+}
+
+enum Token {
+    Word { spans: Vec<Span<'static>>, space_before: bool },
+    Break,
+}
+
+// The part of the line from column `skip` that fits in `take` columns.
+fn crop(line: Line<'static>, skip: usize, take: usize) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut column = 0;
+    for span in line.spans {
+        let mut text = String::new();
+        for c in span.content.chars() {
+            let char_width = display_width(c.encode_utf8(&mut [0; 4]));
+            if column >= skip && column + char_width <= skip + take {
+                text.push(c);
+            }
+            column += char_width;
+        }
+        if !text.is_empty() {
+            spans.push(Span::styled(text, span.style));
+        }
+    }
+    spans
+}
+
+// Text right after a word, with no space between, joins that word.
+fn push_word(tokens: &mut Vec<Token>, span: Span<'static>, space_before: bool) {
+    match tokens.last_mut() {
+        Some(Token::Word { spans, .. }) if !space_before => spans.push(span),
+        _ => tokens.push(Token::Word {
+            spans: vec![span],
+            space_before,
+        }),
+    }
+}
+
+// Puts `marker` before the first line and indents the rest under it.
+fn hang(lines: Vec<Line<'static>>, marker: String, marker_width: usize) -> Vec<Line<'static>> {
+    if lines.is_empty() {
+        return vec![Line::from(Span::styled(marker, Style::default().fg(PRIMARY)))];
+    }
+    let mut marker = Some(marker);
+    lines
+        .into_iter()
+        .map(|line| {
+            let prefix = match marker.take() {
+                Some(marker) => Span::styled(marker, Style::default().fg(PRIMARY)),
+                None => Span::raw(" ".repeat(marker_width)),
+            };
+            indent(line, prefix)
+        })
+        .collect()
+}
+
+fn render_table(header: bool, rows: &[Vec<String>], width: usize) -> Vec<Line<'static>> {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    if columns == 0 {
+        return Vec::new();
+    }
+    let mut widths = vec![0; columns];
+    for row in rows {
+        for (i, cell) in row.iter().enumerate() {
+            widths[i] = widths[i].max(display_width(cell));
+        }
+    }
+
+    // Shrink the widest columns until the table fits, keeping separators
+    let available = width.saturating_sub(3 * (columns - 1));
+    while widths.iter().sum::<usize>() > available {
+        let Some(widest) = widths.iter_mut().max() else { break };
+        if *widest <= 3 {
+            break;
+        }
+        *widest -= 1;
+    }
+
+    let border = Style::default().fg(DIM);
+    let mut lines = Vec::new();
+    for (r, row) in rows.iter().enumerate() {
+        let is_header = header && r == 0;
+        let style = if is_header {
+            Style::default().add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        let mut spans = Vec::new();
+        for (i, column_width) in widths.iter().enumerate() {
+            if i > 0 {
+                spans.push(Span::styled(" │ ", border));
+            }
+            let cell = row.get(i).map(String::as_str).unwrap_or("");
+            spans.push(Span::styled(pad(cell, *column_width), style));
+        }
+        lines.push(Line::from(spans));
+        if is_header {
+            let rule = widths
+                .iter()
+                .map(|w| "─".repeat(*w))
+                .collect::<Vec<_>>()
+                .join("─┼─");
+            lines.push(Line::from(Span::styled(rule, border)));
+        }
+    }
+    lines
+}
+
+fn pad(text: &str, width: usize) -> String {
+    let text = crate::ui::utils::truncate(text, width);
+    let fill = width.saturating_sub(display_width(&text));
+    format!("{}{}", text, " ".repeat(fill))
+}
+
+fn indent(line: Line<'static>, prefix: Span<'static>) -> Line<'static> {
+    let mut spans = vec![prefix];
+    spans.extend(line.spans);
+    Line::from(spans).style(line.style)
+}
+
+fn inline_style(inline: &Inline) -> Style {
+    let mut style = Style::default();
+    if inline.bold {
+        style = style.add_modifier(Modifier::BOLD);
+    }
+    if inline.italic {
+        style = style.add_modifier(Modifier::ITALIC);
+    }
+    if inline.code {
+        style = style.fg(CODE);
+    }
+    if inline.link.is_some() {
+        style = style.fg(PRIMARY).add_modifier(Modifier::UNDERLINED);
+    }
+    style
+}
@@ -1,9 +1,10 @@
 use crate::app::{App, Focus};
+use crate::feed::{SNIPPET_END, SNIPPET_START};
 use crate::ui::colors::{DIM, PRIMARY, SECONDARY, SELECTED_BG, SUCCESS};
 use crate::ui::utils::{feed_icon, time_ago, truncate};
 use ratatui::{
     layout::{Alignment, Constraint, Layout, Rect},
-    style::{Color, Style},
+    style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{Block, Borders, List, ListItem, Paragraph},
     Frame,
@@ -32,6 +33,8 @@ fn render_feed_items(f: &mut Frame, app: &App, area: Rect) {
         format!(" Feed {} ", app.spinner_char())
     } else if app.background_loading {
         format!(" Feed {} updating... ", app.spinner_char())
+    } else if let Some(query) = &app.search_query {
+        format!(" Search: {} ", query)
     } else {
         " Feed ".to_string()
     };
@@ -128,7 +131,12 @@ fn render_items_list(f: &mut Frame, app: &App, area: Rect, feed_title: String, i
                 Span::styled(metadata, Style::default().fg(DIM)),
             ]);
 
-            ListItem::new(vec![first_line, second_line])
+            let mut lines = vec![first_line, second_line];
+            if let Some(snippet) = &item.snippet {
+                lines.push(snippet_line(snippet, available_width));
+            }
+
+            ListItem::new(lines)
                 .style(style)
         })
         .collect();
@@ -146,6 +154,31 @@ fn render_items_list(f: &mut Frame, app: &App, area: Rect, feed_title: String, i
     f.render_stateful_widget(items_list, area, &mut list_state);
 }
 
+fn snippet_line(snippet: &str, max_width: usize) -> Line<'static> {
+    let mut spans = vec![Span::raw("    ")];
+    let mut remaining = max_width.saturating_sub(4);
+    let mut highlighted = false;
+    let text = snippet.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    for (i, part) in text.split([SNIPPET_START, SNIPPET_END]).enumerate() {
+        if i > 0 {
+            highlighted = !highlighted;
+        }
+        if part.is_empty() || remaining == 0 {
+            continue;
+        }
+        let part = truncate(part, remaining);
+        remaining = remaining.saturating_sub(part.chars().count());
+        let style = if highlighted {
+            Style::default().fg(PRIMARY).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(DIM)
+        };
+        spans.push(Span::styled(part, style));
+    }
+    Line::from(spans)
+}
+
 fn render_status(f: &mut Frame, app: &App, area: Rect) {
     let is_loading = app.loading || app.background_loading || app.article_loading;
     let status_text = if is_loading {
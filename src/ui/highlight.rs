@@ -0,0 +1,91 @@
+use crate::ui::colors::CODE;
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Mutex, OnceLock, PoisonError};
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{FontStyle, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+const THEME: &str = "base16-ocean.dark";
+
+// The reader redraws on every tick, so highlighted blocks are kept instead
+// of running the highlighter each frame.
+const CACHE_LIMIT: usize = 64;
+
+static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+static CACHE: OnceLock<Mutex<HashMap<u64, Vec<Line<'static>>>>> = OnceLock::new();
+
+/// Highlights `code` as `language` (a name or file extension, like `rust` or
+/// `py`), one line per source line. Code in a language we don't know is
+/// drawn in the plain code color.
+pub fn highlight(code: &str, language: Option<&str>) -> Vec<Line<'static>> {
+    let mut hasher = DefaultHasher::new();
+    (code, language).hash(&mut hasher);
+    let key = hasher.finish();
+
+    let mut cache = CACHE
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    if let Some(lines) = cache.get(&key) {
+        return lines.clone();
+    }
+    let lines = language
+        .and_then(|language| highlight_as(code, language))
+        .unwrap_or_else(|| plain(code));
+    if cache.len() >= CACHE_LIMIT {
+        cache.clear();
+    }
+    cache.insert(key, lines.clone());
+    lines
+}
+
+fn highlight_as(code: &str, language: &str) -> Option<Vec<Line<'static>>> {
+    let syntaxes = SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines);
+    let syntax = syntaxes.find_syntax_by_token(language)?;
+    let theme = THEMES.get_or_init(ThemeSet::load_defaults).themes.get(THEME)?;
+    let mut highlighter = HighlightLines::new(syntax, theme);
+
+    let mut lines = Vec::new();
+    for line in LinesWithEndings::from(code) {
+        let spans = highlighter
+            .highlight_line(line, syntaxes)
+            .ok()?
+            .into_iter()
+            .map(|(style, text)| (style, text.trim_end_matches(['\n', '\r'])))
+            .filter(|(_, text)| !text.is_empty())
+            .map(|(style, text)| Span::styled(text.to_string(), to_style(style)))
+            .collect::<Vec<_>>();
+        lines.push(Line::from(spans));
+    }
+    Some(lines)
+}
+
+fn plain(code: &str) -> Vec<Line<'static>> {
+    code.lines()
+        .map(|line| Line::from(Span::styled(line.to_string(), Style::default().fg(CODE))))
+        .collect()
+}
+
+// The theme's background is left out so blocks sit on the reader's own.
+fn to_style(style: syntect::highlighting::Style) -> Style {
+    let fg = style.foreground;
+    let mut out = Style::default().fg(Color::Rgb(fg.r, fg.g, fg.b));
+    if style.font_style.contains(FontStyle::BOLD) {
+        out = out.add_modifier(Modifier::BOLD);
+    }
+    if style.font_style.contains(FontStyle::ITALIC) {
+        out = out.add_modifier(Modifier::ITALIC);
+    }
+    if style.font_style.contains(FontStyle::UNDERLINE) {
+        out = out.add_modifier(Modifier::UNDERLINED);
+    }
+    out
+}
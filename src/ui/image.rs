@@ -0,0 +1,69 @@
+use image::imageops::{self, FilterType};
+use image::RgbImage;
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span},
+};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+const MAX_ROWS: u32 = 30;
+const CACHE_LIMIT: usize = 32;
+
+static CACHE: OnceLock<Mutex<HashMap<u64, Vec<Line<'static>>>>> = OnceLock::new();
+
+/// The columns and rows an image takes, drawn at its own size when it fits
+/// in `max_width` columns and `MAX_ROWS` rows, keeping its shape. `cell` is
+/// the pixel size of a terminal cell.
+pub fn size_in_cells(image: &RgbImage, max_width: usize, cell: (u16, u16)) -> (u16, u16) {
+    let (width, height) = image.dimensions();
+    let (cell_width, cell_height) = (u64::from(cell.0), u64::from(cell.1));
+    let mut columns = u64::from(width)
+        .div_ceil(cell_width)
+        .clamp(1, max_width.max(1) as u64);
+    let mut rows = (u64::from(height) * columns * cell_width).div_ceil(u64::from(width).max(1) * cell_height);
+    if rows > u64::from(MAX_ROWS) {
+        columns = (columns * u64::from(MAX_ROWS) / rows).max(1);
+        rows = u64::from(MAX_ROWS);
+    }
+    (columns as u16, rows.max(1) as u16)
+}
+
+/// Draws the image with `▀`, each cell showing two pixels: the upper one as
+/// the foreground and the lower one as the background.
+pub fn half_blocks(src: &str, image: &RgbImage, columns: u16, rows: u16) -> Vec<Line<'static>> {
+    let mut hasher = DefaultHasher::new();
+    (src, columns, rows).hash(&mut hasher);
+    let key = hasher.finish();
+
+    // Scaling is too slow to repeat on every frame
+    let mut cache = CACHE
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    if let Some(lines) = cache.get(&key) {
+        return lines.clone();
+    }
+
+    let scaled = imageops::resize(image, u32::from(columns), u32::from(rows) * 2, FilterType::Triangle);
+    let color = |x, y| {
+        let pixel = scaled.get_pixel(x, y);
+        Color::Rgb(pixel[0], pixel[1], pixel[2])
+    };
+    let lines: Vec<Line<'static>> = (0..u32::from(rows))
+        .map(|row| {
+            let spans: Vec<Span<'static>> = (0..u32::from(columns))
+                .map(|x| Span::styled("▀", Style::default().fg(color(x, row * 2)).bg(color(x, row * 2 + 1))))
+                .collect();
+            Line::from(spans)
+        })
+        .collect();
+
+    if cache.len() >= CACHE_LIMIT {
+        cache.clear();
+    }
+    cache.insert(key, lines.clone());
+    lines
+}
@@ -1,6 +1,9 @@
 mod colors;
 mod dialogs;
+mod document;
 mod feed_list;
+mod highlight;
+mod image;
 mod reader;
 mod sidebar;
 mod utils;
@@ -8,10 +11,12 @@ mod utils;
 use crate::app::{App, Focus};
 use ratatui::{layout::{Constraint, Layout}, Frame};
 
-pub fn render(f: &mut Frame, app: &App) {
+pub use reader::ReaderLayout;
+
+/// Draws the app. Outside the reader, the layout is empty.
+pub fn render(f: &mut Frame, app: &App) -> ReaderLayout {
     if app.focus == Focus::Reader {
-        reader::render(f, app);
-        return;
+        return reader::render(f, app);
     }
 
     let chunks = Layout::horizontal([Constraint::Length(28), Constraint::Min(0)]).split(f.area());
@@ -27,7 +32,28 @@ pub fn render(f: &mut Frame, app: &App) {
         dialogs::render_filter_dialog(f, app);
     }
 
+    if app.search_mode {
+        dialogs::render_search_dialog(f, app);
+    }
+
     if app.tag_editor_mode {
         dialogs::render_tag_editor(f, app);
     }
+
+    if app.edit_feed_mode {
+        dialogs::render_edit_feed_dialog(f, app);
+    }
+
+    if app.mark_older_mode {
+        dialogs::render_mark_older_dialog(f, app);
+    }
+
+    if app.feed_picker_mode {
+        dialogs::render_feed_picker(f, app);
+    }
+
+    if app.show_health {
+        dialogs::render_health_dialog(f, app);
+    }
+    ReaderLayout::default()
 }
@@ -1,26 +1,52 @@
 use crate::app::App;
-use crate::ui::colors::{DIM, PRIMARY, SECONDARY, SUCCESS};
+use crate::graphics::{self, Placement};
+use crate::ui::colors::{DIM, PRIMARY, SECONDARY, SUCCESS, WARNING};
+use crate::ui::document;
 use crate::ui::utils::truncate;
 use ratatui::{
-    layout::{Alignment, Constraint, Layout, Rect},
+    layout::{Alignment, Constraint, Layout, Margin, Rect},
     style::{Color, Style},
     text::{Line, Span},
     widgets::{Block, Borders, Paragraph},
     Frame,
 };
 
-pub fn render(f: &mut Frame, app: &App) {
+/// What the reader laid out that the app needs after drawing: where the
+/// terminal should draw images, and the lines search matches are on.
+#[derive(Default)]
+pub struct ReaderLayout {
+    pub placements: Vec<Placement>,
+    pub matches: Vec<usize>,
+}
+
+struct SearchMatch {
+    line: usize,
+    start: usize,
+    end: usize,
+}
+
+pub fn render(f: &mut Frame, app: &App) -> ReaderLayout {
     let chunks = Layout::vertical([Constraint::Min(0), Constraint::Length(3)]).split(f.area());
 
+    let mut layout = ReaderLayout::default();
     if app.article_loading {
         render_loading(f, app, chunks[0]);
     } else if let Some(article) = &app.current_article {
-        render_article(f, app, article, chunks[0]);
+        layout = render_article(f, app, article, chunks[0]);
     } else {
         render_no_article(f, chunks[0]);
     }
 
-    render_help(f, chunks[1]);
+    if app.link_hint_mode {
+        render_link_hint(f, app, chunks[1]);
+    } else if app.reader_search_mode {
+        render_search_prompt(f, app, chunks[1]);
+    } else if let Some(query) = &app.reader_search {
+        render_search_help(f, query, chunks[1]);
+    } else {
+        render_help(f, chunks[1]);
+    }
+    layout
 }
 
 fn render_loading(f: &mut Frame, app: &App, area: Rect) {
@@ -36,66 +62,49 @@ fn render_loading(f: &mut Frame, app: &App, area: Rect) {
     f.render_widget(loading, area);
 }
 
-fn render_article(f: &mut Frame, app: &App, article: &crate::reader::Article, area: Rect) {
+fn render_article(f: &mut Frame, app: &App, article: &crate::reader::Article, area: Rect) -> ReaderLayout {
     let content_width = area.width.saturating_sub(4) as usize;
-    let wrapped_lines: Vec<Line> = article
-        .content
-        .lines()
-        .flat_map(|line| {
-            if line.is_empty() {
-                vec![Line::from("")]
-            } else if line.starts_with("# ") {
-                let text = line.trim_start_matches("# ").to_string();
-                vec![Line::from(vec![
-                    Span::styled(text, Style::default().fg(PRIMARY).add_modifier(ratatui::style::Modifier::BOLD))
-                ])]
-            } else if line.starts_with("## ") {
-                let text = line.trim_start_matches("## ").to_string();
-                vec![Line::from(vec![
-                    Span::styled(text, Style::default().fg(SECONDARY).add_modifier(ratatui::style::Modifier::BOLD))
-                ])]
-            } else if line.starts_with("### ") {
-                let text = line.trim_start_matches("### ").to_string();
-                vec![Line::from(vec![
-                    Span::styled(text, Style::default().fg(SUCCESS).add_modifier(ratatui::style::Modifier::BOLD))
-                ])]
-            } else if line.starts_with("• ") {
-                textwrap::wrap(line, content_width.saturating_sub(2))
-                    .into_iter()
-                    .enumerate()
-                    .map(|(i, s)| {
-                        if i == 0 {
-                            Line::from(s.to_string())
-                        } else {
-                            Line::from(format!("  {}", s))
-                        }
-                    })
-                    .collect::<Vec<_>>()
-            } else if line.starts_with("  ") && !line.starts_with("   ") {
-                textwrap::wrap(line, content_width)
-                    .into_iter()
-                    .map(|s| Line::from(vec![
-                        Span::styled(s.to_string(), Style::default().fg(DIM).add_modifier(ratatui::style::Modifier::ITALIC))
-                    ]))
-                    .collect::<Vec<_>>()
-            } else {
-                textwrap::wrap(line, content_width)
-                    .into_iter()
-                    .map(|s| Line::from(format_inline_styles(s.to_string())))
-                    .collect::<Vec<_>>()
-            }
-        })
-        .collect();
+    let images = document::Images {
+        loaded: &app.article_images,
+        protocol: app.image_output,
+        cell: graphics::cell_size(),
+    };
+    let rendered = document::render(&article.document(), content_width, app.article_hscroll as usize, &images);
+    let wrapped_lines = rendered.lines;
+    let matches = app
+        .reader_search
+        .as_deref()
+        .map(|query| find_matches(&wrapped_lines, query))
+        .unwrap_or_default();
 
     let total_lines = wrapped_lines.len();
     let visible_height = area.height.saturating_sub(2) as usize;
     let max_scroll = total_lines.saturating_sub(visible_height);
     let scroll = (app.article_scroll as usize).min(max_scroll);
 
+    let match_style = Style::default().fg(Color::Black).bg(WARNING);
+    let current_style = Style::default().fg(Color::Black).bg(PRIMARY);
     let visible_lines: Vec<Line> = wrapped_lines
         .into_iter()
+        .enumerate()
         .skip(scroll)
         .take(visible_height)
+        .map(|(i, line)| {
+            let ranges: Vec<(usize, usize, Style)> = matches
+                .iter()
+                .enumerate()
+                .filter(|(_, m)| m.line == i)
+                .map(|(n, m)| {
+                    let style = if app.reader_match == Some(n) { current_style } else { match_style };
+                    (m.start, m.end, style)
+                })
+                .collect();
+            if ranges.is_empty() {
+                line
+            } else {
+                highlight_ranges(line, &ranges)
+            }
+        })
         .collect();
 
     let scroll_indicator = if total_lines > visible_height {
@@ -104,8 +113,15 @@ fn render_article(f: &mut Frame, app: &App, article: &crate::reader::Article, ar
         String::new()
     };
 
+    let match_indicator = match (&app.reader_search, app.reader_match) {
+        (None, _) => String::new(),
+        (Some(_), _) if matches.is_empty() => "[no matches] ".to_string(),
+        (Some(_), Some(current)) => format!("[match {}/{}] ", current + 1, matches.len()),
+        (Some(_), None) => format!("[{} matches] ", matches.len()),
+    };
+
     let title_text = truncate(&article.title, 60);
-    let title = format!(" {} {}", title_text, scroll_indicator);
+    let title = format!(" {} {}{}", title_text, scroll_indicator, match_indicator);
 
     let content = Paragraph::new(visible_lines)
         .style(Style::default().fg(Color::White))
@@ -116,62 +132,79 @@ fn render_article(f: &mut Frame, app: &App, article: &crate::reader::Article, ar
                 .border_style(Style::default().fg(PRIMARY)),
         );
     f.render_widget(content, area);
+
+    // The cells of visible images are skipped when drawing, so the text
+    // layer doesn't paint over them
+    let inner = area.inner(Margin::new(1, 1));
+    let mut placements = Vec::new();
+    for slot in rendered.images {
+        let top = slot.line.max(scroll);
+        let bottom = (slot.line + usize::from(slot.height)).min(scroll + visible_height);
+        if top >= bottom {
+            continue;
+        }
+        let image_area = Rect::new(
+            inner.x + slot.x as u16,
+            inner.y + (top - scroll) as u16,
+            slot.width,
+            (bottom - top) as u16,
+        )
+        .intersection(inner);
+        for position in image_area.positions() {
+            f.buffer_mut()[position].set_skip(true);
+        }
+        placements.push(Placement {
+            src: slot.src,
+            area: image_area,
+            crop_top: (top - slot.line) as u16,
+            rows: slot.height,
+        });
+    }
+    ReaderLayout {
+        placements,
+        matches: matches.iter().map(|m| m.line).collect(),
+    }
 }
 
-fn format_inline_styles(text: String) -> Vec<Span<'static>> {
-    let mut spans = Vec::new();
-    let mut current_text = String::new();
-    let mut chars = text.chars().peekable();
-    let mut in_bold = false;
-    let mut in_italic = false;
-
-    while let Some(c) = chars.next() {
-        if c == '*' {
-            if chars.peek() == Some(&'*') {
-                chars.next();
-                if !current_text.is_empty() {
-                    let style = if in_italic {
-                        Style::default().add_modifier(ratatui::style::Modifier::ITALIC)
-                    } else {
-                        Style::default()
-                    };
-                    spans.push(Span::styled(current_text.clone(), style));
-                    current_text.clear();
-                }
-                in_bold = !in_bold;
+// Case-insensitive. Lines are searched as shown, so a phrase broken over two
+// lines isn't found.
+fn find_matches(lines: &[Line], query: &str) -> Vec<SearchMatch> {
+    let fold = |c: char| c.to_lowercase().next().unwrap_or(c);
+    let query: Vec<char> = query.chars().map(fold).collect();
+    let mut matches = Vec::new();
+    for (line, text) in lines.iter().enumerate() {
+        let text: Vec<char> = text.spans.iter().flat_map(|span| span.content.chars()).map(fold).collect();
+        let mut start = 0;
+        while start + query.len() <= text.len() {
+            if text[start..start + query.len()] == query[..] {
+                matches.push(SearchMatch { line, start, end: start + query.len() });
+                start += query.len();
             } else {
-                if !current_text.is_empty() {
-                    let style = if in_bold {
-                        Style::default().add_modifier(ratatui::style::Modifier::BOLD)
-                    } else {
-                        Style::default()
-                    };
-                    spans.push(Span::styled(current_text.clone(), style));
-                    current_text.clear();
-                }
-                in_italic = !in_italic;
+                start += 1;
             }
-        } else {
-            current_text.push(c);
         }
     }
+    matches
+}
 
-    if !current_text.is_empty() {
-        let mut style = Style::default();
-        if in_bold {
-            style = style.add_modifier(ratatui::style::Modifier::BOLD);
-        }
-        if in_italic {
-            style = style.add_modifier(ratatui::style::Modifier::ITALIC);
+// Restyles the characters in each `start..end` range of the line.
+fn highlight_ranges(line: Line<'static>, ranges: &[(usize, usize, Style)]) -> Line<'static> {
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    let mut index = 0;
+    for span in line.spans {
+        for c in span.content.chars() {
+            let style = ranges
+                .iter()
+                .find(|(start, end, _)| (*start..*end).contains(&index))
+                .map_or(span.style, |(_, _, highlight)| span.style.patch(*highlight));
+            match spans.last_mut() {
+                Some(last) if last.style == style => last.content.to_mut().push(c),
+                _ => spans.push(Span::styled(c.to_string(), style)),
+            }
+            index += 1;
         }
-        spans.push(Span::styled(current_text, style));
-    }
-
-    if spans.is_empty() {
-        vec![Span::raw(text)]
-    } else {
-        spans
     }
+    Line::from(spans).style(line.style)
 }
 
 fn render_no_article(f: &mut Frame, area: Rect) {
@@ -197,9 +230,57 @@ fn render_help(f: &mut Frame, area: Rect) {
         Span::raw(" scroll  "),
         Span::styled("Space/b", Style::default().fg(SUCCESS)),
         Span::raw(" page  "),
+        Span::styled("J/K", Style::default().fg(SUCCESS)),
+        Span::raw(" next/prev  "),
+        Span::styled("n", Style::default().fg(SUCCESS)),
+        Span::raw(" next unread  "),
+        Span::styled("f", Style::default().fg(SUCCESS)),
+        Span::raw(" follow link  "),
+        Span::styled("/", Style::default().fg(SUCCESS)),
+        Span::raw(" search  "),
         Span::styled("o", Style::default().fg(SUCCESS)),
         Span::raw(" open in browser"),
     ]))
     .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(DIM)));
     f.render_widget(help, area);
 }
+
+fn render_link_hint(f: &mut Frame, app: &App, area: Rect) {
+    let prompt = Paragraph::new(Line::from(vec![
+        Span::styled("Link: ", Style::default().fg(PRIMARY)),
+        Span::raw(format!("{}  ", app.link_hint_input)),
+        Span::styled("Enter", Style::default().fg(SUCCESS)),
+        Span::raw(" read  "),
+        Span::styled("o", Style::default().fg(SUCCESS)),
+        Span::raw(" open in browser  "),
+        Span::styled("Esc", Style::default().fg(SUCCESS)),
+        Span::raw(" cancel"),
+    ]))
+    .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(PRIMARY)));
+    f.render_widget(prompt, area);
+    f.set_cursor_position((area.x + 7 + app.link_hint_input.len() as u16, area.y + 1));
+}
+
+fn render_search_prompt(f: &mut Frame, app: &App, area: Rect) {
+    let prompt = Paragraph::new(Line::from(vec![
+        Span::styled("/", Style::default().fg(PRIMARY)),
+        Span::raw(app.reader_search_input.as_str()),
+    ]))
+    .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(PRIMARY)));
+    f.render_widget(prompt, area);
+    f.set_cursor_position((area.x + 2 + app.reader_search_input.chars().count() as u16, area.y + 1));
+}
+
+fn render_search_help(f: &mut Frame, query: &str, area: Rect) {
+    let help = Paragraph::new(Line::from(vec![
+        Span::styled(format!("/{}  ", query), Style::default().fg(PRIMARY)),
+        Span::styled("n/N", Style::default().fg(SUCCESS)),
+        Span::raw(" next/prev match  "),
+        Span::styled("/", Style::default().fg(SUCCESS)),
+        Span::raw(" new search  "),
+        Span::styled("Esc", Style::default().fg(SUCCESS)),
+        Span::raw(" clear search (then n next unread)"),
+    ]))
+    .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(DIM)));
+    f.render_widget(help, area);
+}
@@ -1,5 +1,6 @@
-use crate::app::{App, Focus};
-use crate::ui::colors::{DIM, PRIMARY, SECONDARY, SELECTED_BG, SUCCESS};
+use crate::app::{App, Focus, SidebarEntry};
+use crate::smart_feed::SmartFeedKind;
+use crate::ui::colors::{DIM, PRIMARY, SECONDARY, SELECTED_BG, SUCCESS, WARNING};
 use crate::ui::utils::{feed_icon, truncate};
 use ratatui::{
     layout::{Constraint, Layout, Rect},
@@ -48,59 +49,36 @@ fn render_feeds_list(f: &mut Frame, app: &App, area: Rect) {
     let is_focused = app.focus == Focus::Feeds;
     let mut items: Vec<ListItem> = Vec::new();
 
-    let mut offset = 0;
-
-    if app.show_all && app.filter.is_empty() {
-        let selected = app.feed_index == offset;
-        let style = if selected {
-            Style::default().fg(SECONDARY).bg(SELECTED_BG)
-        } else {
-            Style::default().fg(SECONDARY)
-        };
-        items.push(ListItem::new("  ★ All").style(style));
-        offset += 1;
-    }
-
-    if app.filter.is_empty() {
-        let selected = app.feed_index == offset;
-        let style = if selected {
-            Style::default().fg(Color::Red).bg(SELECTED_BG)
-        } else {
-            Style::default().fg(Color::Red)
-        };
-        items.push(ListItem::new("  ❤ Liked").style(style));
-        offset += 1;
-
-        let selected_articles = app.feed_index == offset;
-        let article_style = if selected_articles {
-            Style::default().fg(Color::Rgb(100, 149, 237)).bg(SELECTED_BG)
-        } else {
-            Style::default().fg(Color::Rgb(100, 149, 237))
-        };
-        items.push(ListItem::new("  \u{f15c} Articles").style(article_style));
-        offset += 1;
-
-        let selected_videos = app.feed_index == offset;
-        let video_style = if selected_videos {
-            Style::default().fg(Color::Rgb(255, 99, 71)).bg(SELECTED_BG)
-        } else {
-            Style::default().fg(Color::Rgb(255, 99, 71))
-        };
-        items.push(ListItem::new("  \u{f03d} Videos").style(video_style));
-        offset += 1;
-    }
-
-    let filtered_sources = app.get_filtered_sources();
-    for (display_idx, (_, source)) in filtered_sources.iter().enumerate() {
-        let idx = display_idx + offset;
+    for (idx, entry) in app.sidebar_entries().into_iter().enumerate() {
         let selected = idx == app.feed_index;
-        let icon = feed_icon(&source.url);
+        let (line, color) = match entry {
+            SidebarEntry::Smart(smart_idx) => {
+                let feed = &app.smart_feeds[smart_idx];
+                let (icon, color) = smart_feed_style(feed.kind);
+                let unread = app.unread_counts.smart.get(smart_idx).copied().unwrap_or(0);
+                let (name, count) = label_with_count(&feed.name, 20, unread);
+                (Line::from(vec![Span::raw(format!("  {} {}", icon, name)), count]), color)
+            }
+            SidebarEntry::Source(source_idx) => {
+                let source = &app.sources[source_idx];
+                let icon = feed_icon(&source.url);
+                let unread = app.unread_counts.feeds.get(&source.name).copied().unwrap_or(0);
+                let failing = app.is_feed_failing(&source.name);
+                let (name, count) = label_with_count(&source.name, if failing { 18 } else { 20 }, unread);
+                let mut spans = vec![Span::raw(format!("  {} {}", icon, name))];
+                if failing {
+                    spans.push(Span::styled(" ⚠", Style::default().fg(WARNING)));
+                }
+                spans.push(count);
+                (Line::from(spans), Color::White)
+            }
+        };
         let style = if selected {
-            Style::default().fg(Color::White).bg(SELECTED_BG)
+            Style::default().fg(color).bg(SELECTED_BG)
         } else {
-            Style::default().fg(Color::White)
+            Style::default().fg(color)
         };
-        items.push(ListItem::new(format!("  {} {}", icon, truncate(&source.name, 20))).style(style));
+        items.push(ListItem::new(line).style(style));
     }
 
     let feeds_block = Block::default()
@@ -116,6 +94,27 @@ fn render_feeds_list(f: &mut Frame, app: &App, area: Rect) {
     f.render_stateful_widget(feeds_list, area, &mut list_state);
 }
 
+// Truncates the label so that it and the unread count fit in `width`. The
+// count is left out when there is nothing unread.
+fn label_with_count(label: &str, width: usize, unread: usize) -> (String, Span<'static>) {
+    if unread == 0 {
+        return (truncate(label, width), Span::raw(""));
+    }
+    let count = format!(" {}", unread);
+    let label = truncate(label, width.saturating_sub(count.len()));
+    (label, Span::styled(count, Style::default().fg(PRIMARY).add_modifier(Modifier::BOLD)))
+}
+
+fn smart_feed_style(kind: SmartFeedKind) -> (&'static str, Color) {
+    match kind {
+        SmartFeedKind::All => ("★", SECONDARY),
+        SmartFeedKind::Liked => ("❤", Color::Red),
+        SmartFeedKind::Articles => ("\u{f15c}", Color::Rgb(100, 149, 237)),
+        SmartFeedKind::Videos => ("\u{f03d}", Color::Rgb(255, 99, 71)),
+        SmartFeedKind::Saved => ("\u{f002}", PRIMARY),
+    }
+}
+
 fn render_tags_list(f: &mut Frame, app: &App, area: Rect) {
     let is_focused = app.focus == Focus::Tags;
     let tags = app.get_all_tags();
@@ -124,12 +123,15 @@ fn render_tags_list(f: &mut Frame, app: &App, area: Rect) {
     for (idx, tag) in tags.iter().enumerate() {
         let selected = idx == app.tag_index && is_focused;
         let feed_count = app.get_feeds_by_tag(tag).len();
+        let unread = app.unread_counts.tags.get(tag).copied().unwrap_or(0);
         let style = if selected {
             Style::default().fg(Color::White).bg(SELECTED_BG)
         } else {
             Style::default().fg(Color::White)
         };
-        items.push(ListItem::new(format!("  # {} ({})", truncate(tag, 15), feed_count)).style(style));
+        let (name, count) = label_with_count(tag, 15, unread);
+        let line = Line::from(vec![Span::raw(format!("  # {} ({})", name, feed_count)), count]);
+        items.push(ListItem::new(line).style(style));
     }
 
     let tags_block = Block::default()
@@ -149,18 +151,28 @@ fn render_help(f: &mut Frame, app: &App, area: Rect) {
     if !app.show_tooltips {
         return;
     }
-    let help = Paragraph::new(vec![
-        Line::from(vec![
-            Span::styled("a", Style::default().fg(SUCCESS)),
-            Span::raw(" add "),
-            Span::styled("t", Style::default().fg(SUCCESS)),
-            Span::raw(" tag "),
-            Span::styled("r", Style::default().fg(SUCCESS)),
-            Span::raw(" refresh "),
-            Span::styled("l", Style::default().fg(SUCCESS)),
-            Span::raw(" like"),
-        ]),
-    ])
+    // Every key types into the filter, so the usual shortcuts don't apply
+    let help = if app.filter_mode {
+        Paragraph::new(Line::from(vec![
+            Span::styled("Enter", Style::default().fg(SUCCESS)),
+            Span::raw(" done "),
+            Span::styled("Esc", Style::default().fg(SUCCESS)),
+            Span::raw(" clear"),
+        ]))
+    } else {
+        Paragraph::new(vec![
+            Line::from(vec![
+                Span::styled("a", Style::default().fg(SUCCESS)),
+                Span::raw(" add "),
+                Span::styled("t", Style::default().fg(SUCCESS)),
+                Span::raw(" tag "),
+                Span::styled("r", Style::default().fg(SUCCESS)),
+                Span::raw(" refresh "),
+                Span::styled("l", Style::default().fg(SUCCESS)),
+                Span::raw(" like"),
+            ]),
+        ])
+    }
     .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(DIM)));
     f.render_widget(help, area);
 }